@@ -2,9 +2,21 @@
 
 #version 400 core
 
+#include "shaders/material.glsl"
+
 in vec4 fcolour;
+in vec2 f_uv;
 out vec4 outputColor;
+uniform bool showbackfaces;
+
 void main()
 {
-	outputColor = fcolour;
+	vec4 texel = diffuse_texel(f_uv);
+	outputColor = vec4(fcolour.rgb * texel.rgb * material.diffuse, fcolour.a * texel.a * material.opacity);
+
+	// Debug view: hatch back faces in red so wrong winding stands out
+	if (showbackfaces && !gl_FrontFacing) {
+		bool stripe = mod(gl_FragCoord.x + gl_FragCoord.y, 8.0) < 4.0;
+		outputColor = stripe ? vec4(1.0, 0.0, 0.0, 1.0) : vec4(0.25, 0.0, 0.0, 1.0);
+	}
 }
\ No newline at end of file
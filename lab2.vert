@@ -4,7 +4,9 @@
 
 layout(location = 0) in vec4 position;
 layout(location = 1) in vec4 colour;
+layout(location = 3) in vec2 uv;
 out vec4 fcolour;
+out vec2 f_uv;
 uniform mat4 model;
 uniform mat4 view;
 uniform mat4 projection;
@@ -13,6 +15,7 @@ uniform uint colourmode;
 void main()
 {
 	gl_Position = projection * view * model * position;
+	f_uv = uv;
 	
 	if (colourmode == 0) {
 		fcolour = colour;
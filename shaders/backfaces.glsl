@@ -0,0 +1,11 @@
+uniform bool showbackfaces;
+
+// Debug view: hatch back faces in red so wrong winding stands out
+vec4 hatch_back_faces(vec4 colour)
+{
+	if (showbackfaces && !gl_FrontFacing) {
+		bool stripe = mod(gl_FragCoord.x + gl_FragCoord.y, 8.0) < 4.0;
+		return stripe ? vec4(1.0, 0.0, 0.0, 1.0) : vec4(0.25, 0.0, 0.0, 1.0);
+	}
+	return colour;
+}
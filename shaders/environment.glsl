@@ -0,0 +1,29 @@
+// The scene's skybox as seen in shiny and see-through surfaces, bound by
+// `Scene::draw` in src/scene.rs.
+
+#include "lights.glsl"
+#include "material.glsl"
+
+uniform samplerCube environment;
+// False when the scene has no skybox
+uniform bool environment_mapped;
+
+// colour, lit, with the skybox refracted through and reflected off
+// view-space point P with unit normal N, as much as the material says.
+vec3 environment_mix(vec3 colour, vec3 P, vec3 N)
+{
+	if (!environment_mapped)
+		return colour;
+	vec3 I = normalize(P);
+	mat3 to_world = mat3(view_inverse);
+	if (material.refraction > 0.0) {
+		// Bent once on the way in; the way out is left to the imagination
+		vec3 T = refract(I, N, 1.0 / material.ior);
+		colour = mix(colour, texture(environment, to_world * T).rgb, material.refraction);
+	}
+	if (material.reflectivity > 0.0) {
+		vec3 R = reflect(I, N);
+		colour = mix(colour, texture(environment, to_world * R).rgb, material.reflectivity);
+	}
+	return colour;
+}
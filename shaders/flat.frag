@@ -0,0 +1,28 @@
+#version 420 core
+
+// Faceted lighting: the normal is the triangle's own, rebuilt from how the
+// view-space position changes across the screen, so the mesh's smooth
+// vertex normals are ignored.
+
+#include "shading.glsl"
+#include "backfaces.glsl"
+
+in vec4 f_base_colour;
+in vec3 f_position;
+in vec3 f_normal;
+in vec2 f_uv;
+in vec4 f_tangent;
+
+out vec4 outputColor;
+
+void main()
+{
+    // Always faces the eye, so no back-face flip is needed. A normal map
+    // still adds its detail on top of the facets.
+    vec3 N = normalize(cross(dFdx(f_position), dFdy(f_position)));
+    N = mapped_normal(N, f_tangent, f_uv);
+
+    vec4 base = f_base_colour * diffuse_texel(f_uv);
+    vec3 colour = shade(f_position, N, base.rgb, false);
+    outputColor = hatch_back_faces(vec4(colour, base.a * material.opacity));
+}
@@ -0,0 +1,15 @@
+#version 420 core
+
+#include "material.glsl"
+#include "backfaces.glsl"
+
+in vec4 fcolour;
+in vec2 f_uv;
+out vec4 outputColor;
+
+void main()
+{
+    // The texture can't be lit per vertex, so it tints the lit colour,
+    // highlights included.
+    outputColor = hatch_back_faces(fcolour * diffuse_texel(f_uv));
+}
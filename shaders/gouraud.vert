@@ -0,0 +1,35 @@
+#version 420 core
+
+// Per-vertex lighting: the colour is worked out at each vertex and
+// interpolated across the face, so highlights smaller than a triangle
+// get lost. Normal maps are ignored for the same reason.
+
+#include "shading.glsl"
+
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 colour;
+layout(location = 2) in vec3 normal;
+layout(location = 3) in vec2 uv;
+
+out vec4 fcolour;
+out vec2 f_uv;
+
+uniform mat4 model, view, projection;
+uniform uint colourmode;
+
+void main()
+{
+    vec4 base_colour;
+    if (colourmode == 0)
+        base_colour = colour;
+    else
+        base_colour = position * 2.0 + vec4(0.5, 0.5, 0.5, 1.0);
+
+    mat4 mv_matrix = view * model;
+    vec4 P = mv_matrix * position;
+    vec3 N = normalize(transpose(inverse(mat3(mv_matrix))) * normal);
+
+    fcolour = vec4(shade(P.xyz, N, base_colour.rgb, false), base_colour.a * material.opacity);
+    f_uv = uv;
+    gl_Position = projection * P;
+}
@@ -0,0 +1,64 @@
+// The scene's lights, filled in each frame by `LightBuffer::upload` in
+// src/light.rs. Positions and directions are in view space.
+
+const int MAX_LIGHTS = 16;
+
+const int LIGHT_DIRECTIONAL = 0;
+const int LIGHT_POINT = 1;
+const int LIGHT_SPOT = 2;
+
+struct Light {
+	vec4 position;  // xyz, w = kind
+	vec4 direction; // xyz, w = range (0 for no falloff)
+	vec4 colour;    // rgb premultiplied by intensity
+	vec4 cone;      // cosines of the inner and outer cone angles,
+	                // z = shadow map layer or -1
+};
+
+layout(std140, binding = 0) uniform Lights {
+	int light_count;
+	Light lights[MAX_LIGHTS];
+};
+
+// 1 fades point and spot lights with distance
+uniform uint attenuationmode;
+
+// From view space back to world space
+uniform mat4 view_inverse;
+
+// Light arriving at view-space point P from light i, with L set to the
+// unit vector from P towards the light.
+vec3 light_radiance(int i, vec3 P, out vec3 L)
+{
+	Light light = lights[i];
+	int kind = int(light.position.w);
+	if (kind == LIGHT_DIRECTIONAL) {
+		L = -light.direction.xyz;
+		return light.colour.rgb;
+	}
+
+	vec3 to_light = light.position.xyz - P;
+	float dist = length(to_light);
+	L = to_light / max(dist, 1e-4);
+
+	float attenuation = 1.0;
+	if (attenuationmode == 1) {
+		float attenuation_k1 = 0.5;
+		float attenuation_k2 = 0.5;
+		float attenuation_k3 = 0.5;
+		attenuation = 1.0 / (attenuation_k1 + attenuation_k2 * dist + attenuation_k3 * dist * dist);
+
+		// Windowed to reach zero at the range
+		float range = light.direction.w;
+		if (range > 0.0) {
+			float window = clamp(1.0 - pow(dist / range, 4.0), 0.0, 1.0);
+			attenuation *= window * window;
+		}
+	}
+
+	if (kind == LIGHT_SPOT) {
+		float cos_angle = dot(-L, light.direction.xyz);
+		attenuation *= smoothstep(light.cone.y, light.cone.x, cos_angle);
+	}
+	return light.colour.rgb * attenuation;
+}
@@ -0,0 +1,42 @@
+// The surface being drawn, set per draw by `Material::upload` in
+// src/material.rs. Colours multiply the vertex colour, and so does the
+// diffuse map when there is one. A normal map bends the surface normal,
+// and environment.glsl mixes in the skybox.
+
+struct Material {
+	vec3 ambient;
+	vec3 diffuse;
+	vec3 specular;
+	float shininess;
+	vec3 emissive;
+	float opacity;
+	bool diffuse_mapped;
+	bool normal_mapped;
+	float reflectivity;
+	float refraction;
+	float ior;
+};
+
+uniform Material material;
+uniform sampler2D diffusemap;
+uniform sampler2D normalmap;
+
+// White when there's no map, so it can always be multiplied in.
+vec4 diffuse_texel(vec2 uv)
+{
+	return material.diffuse_mapped ? texture(diffusemap, uv) : vec4(1.0);
+}
+
+// The normal map's normal moved from tangent space, given by unit normal
+// N and the interpolated tangent T (w is the bitangent's handedness), into
+// N's space. Just N when there's no map.
+vec3 mapped_normal(vec3 N, vec4 T, vec2 uv)
+{
+	if (!material.normal_mapped)
+		return N;
+	// Interpolation leaves T a little off perpendicular.
+	vec3 tangent = normalize(T.xyz - N * dot(N, T.xyz));
+	vec3 bitangent = cross(N, tangent) * T.w;
+	vec3 n = texture(normalmap, uv).xyz * 2.0 - 1.0;
+	return normalize(mat3(tangent, bitangent, N) * n);
+}
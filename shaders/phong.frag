@@ -0,0 +1,24 @@
+#version 420 core
+
+// Per-fragment lighting with the normal interpolated across the face.
+
+#include "shading.glsl"
+#include "backfaces.glsl"
+
+in vec4 f_base_colour;
+in vec3 f_position;
+in vec3 f_normal;
+in vec2 f_uv;
+in vec4 f_tangent;
+
+out vec4 outputColor;
+
+void main()
+{
+    vec3 N = mapped_normal(normalize(f_normal), f_tangent, f_uv);
+    if (!gl_FrontFacing) N = -N;
+
+    vec4 base = f_base_colour * diffuse_texel(f_uv);
+    vec3 colour = shade(f_position, N, base.rgb, false);
+    outputColor = hatch_back_faces(vec4(colour, base.a * material.opacity));
+}
@@ -0,0 +1,41 @@
+#version 420 core
+
+// Per-fragment lighting: passes view-space position and normal on to
+// the fragment shader, which loops over the scene's lights. Shared by
+// the flat, Phong and Blinn-Phong models, which also normal map.
+
+layout(location = 0) in vec4 position;
+layout(location = 1) in vec4 colour;
+layout(location = 2) in vec3 normal;
+layout(location = 3) in vec2 uv;
+layout(location = 4) in vec4 tangent;
+
+out vec4 f_base_colour;
+out vec3 f_position;
+out vec3 f_normal;
+out vec2 f_uv;
+out vec4 f_tangent;
+
+uniform mat4 model, view, projection;
+uniform uint colourmode;
+
+void main()
+{
+    if (colourmode == 0)
+        f_base_colour = colour;
+    else
+        f_base_colour = position * 2.0 + vec4(0.5, 0.5, 0.5, 1.0);
+
+    mat4 mv_matrix = view * model;
+    vec4 P = mv_matrix * position;
+    // Inverse transpose, so non-uniform scales keep normals perpendicular
+    mat3 normalmatrix = transpose(inverse(mat3(mv_matrix)));
+
+    f_position = P.xyz;
+    f_normal = normalmatrix * normal;
+    f_uv = uv;
+    // Tangents lie in the surface, so they move with it
+    f_tangent = vec4(mat3(mv_matrix) * tangent.xyz, tangent.w);
+
+    gl_Position = projection * P;
+}
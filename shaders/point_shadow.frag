@@ -0,0 +1,16 @@
+#version 420 core
+
+// Stores the distance from the light rather than the projected depth, so
+// the lit shaders can compare without knowing which face they read.
+
+in Vertex {
+    vec3 world;
+} f_in;
+
+// World-space position, w = far plane
+uniform vec4 light;
+
+void main()
+{
+    gl_FragDepth = distance(f_in.world, light.xyz) / light.w;
+}
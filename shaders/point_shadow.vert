@@ -0,0 +1,21 @@
+#version 420 core
+
+// Distance pass into a point light's cube shadow map. With a geometry
+// shader the faces' matrices are applied there; drawing a face at a time,
+// light_matrix is that face's.
+
+layout(location = 0) in vec4 position;
+
+uniform mat4 model;
+uniform mat4 light_matrix;
+
+out Vertex {
+    vec3 world;
+} v_out;
+
+void main()
+{
+    vec4 world = model * position;
+    v_out.world = world.xyz;
+    gl_Position = light_matrix * world;
+}
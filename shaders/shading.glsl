@@ -0,0 +1,47 @@
+// Lighting shared by every shading model: ambient and emissive plus, for
+// each light, Lambert diffuse and a Phong or Blinn-Phong highlight, both
+// blocked by the light's shadow map or cube if it has one. Reflections and
+// refractions of the skybox cover all but the highlights.
+
+#include "lights.glsl"
+#include "material.glsl"
+#include "shadows.glsl"
+#include "environment.glsl"
+
+const vec3 global_ambient = vec3(0.25, 0.25, 0.25);
+
+uniform uint emitmode;
+
+// Colour of view-space point P with unit normal N, for a surface whose
+// vertex colour is base.
+vec3 shade(vec3 P, vec3 N, vec3 base, bool blinn)
+{
+	vec3 V = normalize(-P);
+	vec3 albedo = base * material.diffuse;
+
+	vec3 colour = base * material.ambient * global_ambient;
+	colour += emitmode == 1 ? vec3(1.0, 1.0, 0.8) : material.emissive;
+	vec3 highlights = vec3(0.0);
+
+	for (int i = 0; i < light_count; i++) {
+		vec3 L;
+		vec3 radiance = light_radiance(i, P, L);
+
+		float NdotL = max(dot(N, L), 0.0);
+		vec3 diffuse = NdotL * albedo;
+
+		float highlight;
+		if (blinn) {
+			vec3 H = normalize(L + V);
+			highlight = max(dot(N, H), 0.0);
+		} else {
+			highlight = max(dot(reflect(-L, N), V), 0.0);
+		}
+		vec3 specular = NdotL > 0.0 ? pow(highlight, material.shininess) * material.specular : vec3(0.0);
+
+		float visibility = light_visibility(i, P, N, NdotL);
+		colour += radiance * visibility * diffuse;
+		highlights += radiance * visibility * specular;
+	}
+	return environment_mix(colour, P, N) + highlights;
+}
@@ -0,0 +1,7 @@
+#version 420 core
+
+// Nothing to write but the depth, which is fixed-function.
+
+void main()
+{
+}
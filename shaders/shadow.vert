@@ -0,0 +1,13 @@
+#version 420 core
+
+// Depth-only pass into a shadow map, from the light's point of view.
+
+layout(location = 0) in vec4 position;
+
+uniform mat4 model;
+uniform mat4 light_matrix;
+
+void main()
+{
+    gl_Position = light_matrix * model * position;
+}
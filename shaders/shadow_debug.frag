@@ -0,0 +1,23 @@
+#version 420 core
+
+// One shadow map layer in grey, near black and far white.
+
+in vec2 f_uv;
+out vec4 outputColor;
+
+uniform sampler2DArray depthmaps;
+uniform int layer;
+// Near and far planes of a perspective layer, to undo its non-linear
+// depth; both 0 for an orthographic one.
+uniform vec2 depth_range;
+
+void main()
+{
+    float depth = texture(depthmaps, vec3(f_uv, layer)).r;
+    if (depth_range.y > 0.0) {
+        float n = depth_range.x, f = depth_range.y;
+        float ndc = depth * 2.0 - 1.0;
+        depth = (2.0 * n * f / (f + n - ndc * (f - n)) - n) / (f - n);
+    }
+    outputColor = vec4(vec3(depth), 1.0);
+}
@@ -0,0 +1,12 @@
+#version 420 core
+
+// A quad over the whole viewport with no vertex buffer: corners from the
+// vertex index, drawn as a 4-vertex triangle strip.
+
+out vec2 f_uv;
+
+void main()
+{
+    f_uv = vec2(gl_VertexID & 1, gl_VertexID >> 1);
+    gl_Position = vec4(f_uv * 2.0 - 1.0, 0.0, 1.0);
+}
@@ -0,0 +1,96 @@
+// Shadow maps, rendered each frame by `ShadowMaps::render` in
+// src/shadow.rs. A shadowed light's `cone.z` in the Lights block names its
+// layer of `shadowmaps`, or for a point light its cube of
+// `pointshadowmaps`.
+
+#include "lights.glsl"
+
+const int MAX_SHADOWS = 4;
+const int MAX_POINT_SHADOWS = 4;
+
+uniform sampler2DArrayShadow shadowmaps;
+// From view space to each layer's texture coordinates and depth
+uniform mat4 shadow_matrices[MAX_SHADOWS];
+// Depth subtracted before comparing, more at grazing angles
+uniform float shadow_bias;
+// Half-width of the square of texels averaged: 1 is 3x3
+uniform int shadow_pcf;
+
+// Distances from each point light as a fraction of its far plane
+uniform samplerCubeArrayShadow pointshadowmaps;
+// World-space position of each cube's light, w = far plane
+uniform vec4 point_shadows[MAX_POINT_SHADOWS];
+
+// Directions to spread the point light samples along, the cube's corners
+// and edge midpoints
+const vec3 point_offsets[20] = vec3[](
+	vec3( 1,  1,  1), vec3( 1, -1,  1), vec3(-1, -1,  1), vec3(-1,  1,  1),
+	vec3( 1,  1, -1), vec3( 1, -1, -1), vec3(-1, -1, -1), vec3(-1,  1, -1),
+	vec3( 1,  1,  0), vec3( 1, -1,  0), vec3(-1, -1,  0), vec3(-1,  1,  0),
+	vec3( 1,  0,  1), vec3(-1,  0,  1), vec3( 1,  0, -1), vec3(-1,  0, -1),
+	vec3( 0,  1,  1), vec3( 0, -1,  1), vec3( 0, -1, -1), vec3( 0,  1, -1)
+);
+
+// How much of the light with shadow map `layer` reaches view-space point
+// P, from 0 in shadow to 1 fully lit. NdotL is the cosine between the
+// normal and the light, which scales the bias.
+float shadow_visibility(int layer, vec3 P, float NdotL)
+{
+	if (layer < 0)
+		return 1.0;
+	vec4 coords = shadow_matrices[layer] * vec4(P, 1.0);
+	coords.xyz /= coords.w;
+	// Beyond the far plane nothing was rendered to block it
+	if (coords.z > 1.0)
+		return 1.0;
+
+	float bias = shadow_bias * (1.0 + 4.0 * (1.0 - clamp(NdotL, 0.0, 1.0)));
+	vec2 texel = 1.0 / vec2(textureSize(shadowmaps, 0).xy);
+	float lit = 0.0;
+	for (int x = -shadow_pcf; x <= shadow_pcf; x++) {
+		for (int y = -shadow_pcf; y <= shadow_pcf; y++) {
+			vec2 uv = coords.xy + vec2(x, y) * texel;
+			lit += texture(shadowmaps, vec4(uv, layer, coords.z - bias));
+		}
+	}
+	int side = 2 * shadow_pcf + 1;
+	return lit / float(side * side);
+}
+
+// As shadow_visibility, for the point light with cube `cube`. The point is
+// pushed out along unit normal N by about a texel, which keeps the surface
+// from shadowing itself at any angle.
+float point_shadow_visibility(int cube, vec3 P, vec3 N)
+{
+	if (cube < 0)
+		return 1.0;
+	vec4 light = point_shadows[cube];
+	vec3 world = (view_inverse * vec4(P, 1.0)).xyz;
+	vec3 normal = normalize(mat3(view_inverse) * N);
+	float texel = 2.0 / float(textureSize(pointshadowmaps, 0).x);
+
+	float dist = distance(world, light.xyz);
+	vec3 to_point = world + normal * dist * texel * 1.5 - light.xyz;
+	float depth = length(to_point) / light.w - shadow_bias;
+	if (depth > 1.0)
+		return 1.0;
+	if (shadow_pcf == 0)
+		return texture(pointshadowmaps, vec4(to_point, cube), depth);
+
+	// Offsets scale with distance so they span the same texels anywhere
+	float spread = length(to_point) * texel * float(shadow_pcf);
+	float lit = 0.0;
+	for (int i = 0; i < 20; i++)
+		lit += texture(pointshadowmaps, vec4(to_point + point_offsets[i] * spread, cube), depth);
+	return lit / 20.0;
+}
+
+// How much of light i reaches view-space point P with unit normal N,
+// whichever kind of shadow map it has.
+float light_visibility(int i, vec3 P, vec3 N, float NdotL)
+{
+	int layer = int(lights[i].cone.z);
+	if (int(lights[i].position.w) == LIGHT_POINT)
+		return point_shadow_visibility(layer, P, N);
+	return shadow_visibility(layer, P, NdotL);
+}
@@ -0,0 +1,11 @@
+#version 420 core
+
+in vec3 f_direction;
+out vec4 outputColor;
+
+uniform samplerCube skybox;
+
+void main()
+{
+    outputColor = vec4(texture(skybox, f_direction).rgb, 1.0);
+}
@@ -0,0 +1,19 @@
+#version 420 core
+
+// A quad over the whole viewport on the far plane, so with the depth test
+// at LEQUAL it only shows where nothing else was drawn. Each corner looks
+// out along its own direction into the cube map.
+
+// Inverse of projection * view, with the view's translation left out so
+// the sky stays put as the camera moves
+uniform mat4 sky_matrix;
+
+out vec3 f_direction;
+
+void main()
+{
+    vec2 corner = vec2(gl_VertexID & 1, gl_VertexID >> 1) * 2.0 - 1.0;
+    gl_Position = vec4(corner, 1.0, 1.0);
+    vec4 world = sky_matrix * gl_Position;
+    f_direction = world.xyz / world.w;
+}
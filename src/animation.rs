@@ -0,0 +1,281 @@
+//! Data-driven node animation. A node carries a list of tracks, applied
+//! in order on top of its transform from the scene file: keyframe tracks
+//! replace a property outright, procedural drivers add to whatever the
+//! property is by then.
+
+use glam::{EulerRot, Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::scene::Transform;
+
+/// Shapes the interpolation between a keyframe and the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Easing {
+    #[default]
+    Linear,
+    /// Starts slow (quadratic).
+    EaseIn,
+    /// Ends slow (quadratic).
+    EaseOut,
+    /// Slow at both ends (smoothstep).
+    EaseInOut,
+    /// Holds this keyframe's value until the next one.
+    Step,
+}
+
+impl Easing {
+    /// Maps progress `t` in [0, 1] through the curve.
+    pub fn apply(self, t: f32) -> f32 {
+        let t = t.clamp(0.0, 1.0);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => 1.0 - (1.0 - t) * (1.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Step => {
+                if t < 1.0 {
+                    0.0
+                } else {
+                    1.0
+                }
+            }
+        }
+    }
+}
+
+/// What a keyframe track does outside its first and last keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Repeat {
+    /// Holds the last value.
+    #[default]
+    Once,
+    Loop,
+    /// Plays forwards then backwards.
+    PingPong,
+}
+
+impl Repeat {
+    /// Folds time `t` since the first key into [0, duration].
+    pub fn apply(self, t: f32, duration: f32) -> f32 {
+        if duration <= 0.0 {
+            return 0.0;
+        }
+        match self {
+            Repeat::Once => t.clamp(0.0, duration),
+            Repeat::Loop => t.rem_euclid(duration),
+            Repeat::PingPong => {
+                let t = t.rem_euclid(2.0 * duration);
+                if t > duration { 2.0 * duration - t } else { t }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Keyframe {
+    /// Seconds.
+    pub time: f32,
+    /// A position, scale, or XYZ Euler angles in degrees.
+    pub value: Vec3,
+    /// Curve towards the next keyframe.
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Property {
+    Translation,
+    Rotation,
+    Scale,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum Track {
+    Translation {
+        keys: Vec<Keyframe>,
+        #[serde(default)]
+        repeat: Repeat,
+    },
+    /// Slerps between the keys' orientations.
+    Rotation {
+        keys: Vec<Keyframe>,
+        #[serde(default)]
+        repeat: Repeat,
+    },
+    Scale {
+        keys: Vec<Keyframe>,
+        #[serde(default)]
+        repeat: Repeat,
+    },
+    /// Adds `axis * amplitude * sin(2 pi t / period + phase)`: a distance
+    /// for translation, degrees about `axis` for rotation, or a fraction of
+    /// the current size for scale.
+    Oscillate {
+        property: Property,
+        axis: Vec3,
+        amplitude: f32,
+        /// Seconds per cycle.
+        period: f32,
+        /// Radians.
+        #[serde(default)]
+        phase: f32,
+    },
+    /// Turns about `axis` at a constant `speed` in degrees per second.
+    Spin { axis: Vec3, speed: f32 },
+}
+
+impl Track {
+    /// Keys must exist and be in time order.
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            Track::Translation { keys, .. } | Track::Rotation { keys, .. } | Track::Scale { keys, .. } => {
+                if keys.is_empty() {
+                    return Err("keyframe track has no keys".into());
+                }
+                if keys.windows(2).any(|pair| pair[1].time < pair[0].time) {
+                    return Err("keyframes are out of time order".into());
+                }
+            }
+            Track::Oscillate { period, .. } if *period <= 0.0 => return Err("oscillation period must be positive".into()),
+            Track::Oscillate { .. } | Track::Spin { .. } => {}
+        }
+        Ok(())
+    }
+
+    /// Applies the track at time `t` seconds to `transform`.
+    pub fn apply(&self, t: f32, transform: &mut Transform) {
+        match self {
+            Track::Translation { keys, repeat } => {
+                let (from, to, progress) = segment(keys, *repeat, t);
+                transform.translation = from.lerp(to, progress);
+            }
+            Track::Rotation { keys, repeat } => {
+                let (from, to, progress) = segment(keys, *repeat, t);
+                transform.rotation = euler(from).slerp(euler(to), progress);
+            }
+            Track::Scale { keys, repeat } => {
+                let (from, to, progress) = segment(keys, *repeat, t);
+                transform.scale = from.lerp(to, progress);
+            }
+            Track::Oscillate { property, axis, amplitude, period, phase } => {
+                let wave = amplitude * (std::f32::consts::TAU * t / period + phase).sin();
+                match property {
+                    Property::Translation => transform.translation += *axis * wave,
+                    Property::Rotation => transform.rotation *= rotation_about(*axis, wave),
+                    Property::Scale => transform.scale *= Vec3::ONE + *axis * wave,
+                }
+            }
+            Track::Spin { axis, speed } => transform.rotation *= rotation_about(*axis, speed * t),
+        }
+    }
+}
+
+/// Runs `tracks` in order over `base` at time `t`.
+pub fn animate(tracks: &[Track], t: f32, base: &Transform) -> Transform {
+    let mut transform = *base;
+    for track in tracks {
+        track.apply(t, &mut transform);
+    }
+    transform
+}
+
+fn euler(degrees: Vec3) -> Quat {
+    let r = degrees * std::f32::consts::PI / 180.0;
+    Quat::from_euler(EulerRot::XYZ, r.x, r.y, r.z)
+}
+
+fn rotation_about(axis: Vec3, degrees: f32) -> Quat {
+    Quat::from_axis_angle(axis.normalize_or(Vec3::Y), degrees.to_radians())
+}
+
+/// The keyframe values either side of time `t` and the eased progress
+/// between them.
+fn segment(keys: &[Keyframe], repeat: Repeat, t: f32) -> (Vec3, Vec3, f32) {
+    let (first, last) = (keys[0], keys[keys.len() - 1]);
+    let t = first.time + repeat.apply(t - first.time, last.time - first.time);
+    match keys.iter().position(|key| key.time > t) {
+        Some(0) => (first.value, first.value, 0.0),
+        None => (last.value, last.value, 0.0),
+        Some(next) => {
+            let (from, to) = (keys[next - 1], keys[next]);
+            let progress = (t - from.time) / (to.time - from.time);
+            (from.value, to.value, from.easing.apply(progress))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(time: f32, value: Vec3) -> Keyframe {
+        Keyframe { time, value, easing: Easing::Linear }
+    }
+
+    #[test]
+    fn easing_curves_start_at_0_and_end_at_1() {
+        for easing in [Easing::Linear, Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut, Easing::Step] {
+            assert_eq!(easing.apply(0.0), 0.0, "{easing:?}");
+            assert_eq!(easing.apply(1.0), 1.0, "{easing:?}");
+        }
+        assert!(Easing::EaseIn.apply(0.5) < 0.5 && Easing::EaseOut.apply(0.5) > 0.5);
+        assert_eq!(Easing::Step.apply(0.99), 0.0);
+    }
+
+    #[test]
+    fn repeat_modes_fold_time() {
+        assert_eq!(Repeat::Once.apply(5.0, 2.0), 2.0);
+        assert_eq!(Repeat::Loop.apply(5.0, 2.0), 1.0);
+        assert_eq!(Repeat::PingPong.apply(3.0, 2.0), 1.0);
+        assert_eq!(Repeat::PingPong.apply(4.5, 2.0), 0.5);
+    }
+
+    #[test]
+    fn translation_keys_interpolate_and_hold() {
+        let track = Track::Translation {
+            keys: vec![key(1.0, Vec3::ZERO), key(3.0, Vec3::new(2.0, 0.0, 0.0))],
+            repeat: Repeat::Once,
+        };
+        let at = |t| animate(std::slice::from_ref(&track), t, &Transform::default()).translation;
+        assert_eq!(at(0.0), Vec3::ZERO);
+        assert_eq!(at(2.0), Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(at(10.0), Vec3::new(2.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn rotation_keys_slerp() {
+        let track = Track::Rotation {
+            keys: vec![key(0.0, Vec3::ZERO), key(1.0, Vec3::new(0.0, 90.0, 0.0))],
+            repeat: Repeat::Loop,
+        };
+        let halfway = animate(&[track], 0.5, &Transform::default()).rotation;
+        assert!(halfway.abs_diff_eq(Quat::from_rotation_y(std::f32::consts::FRAC_PI_4), 1e-5), "{halfway}");
+    }
+
+    #[test]
+    fn drivers_add_to_the_base_transform() {
+        let base = Transform::from_translation(Vec3::new(0.0, 0.0, -1.0));
+        let tracks = [
+            Track::Oscillate {
+                property: Property::Translation,
+                axis: Vec3::Y,
+                amplitude: 0.5,
+                period: 4.0,
+                phase: 0.0,
+            },
+            Track::Spin { axis: Vec3::Y, speed: 90.0 },
+        ];
+        let posed = animate(&tracks, 1.0, &base);
+        assert!(posed.translation.abs_diff_eq(Vec3::new(0.0, 0.5, -1.0), 1e-5), "{}", posed.translation);
+        assert!(posed.rotation.abs_diff_eq(Quat::from_rotation_y(std::f32::consts::FRAC_PI_2), 1e-5));
+    }
+
+    #[test]
+    fn badly_ordered_keys_are_rejected() {
+        let track = Track::Scale {
+            keys: vec![key(1.0, Vec3::ONE), key(0.5, Vec3::ONE)],
+            repeat: Repeat::Once,
+        };
+        assert!(track.check().is_err());
+    }
+}
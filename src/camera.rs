@@ -0,0 +1,61 @@
+use glam::{Mat4, Vec3};
+
+pub struct Camera {
+    pub x: f32,
+    pub y: f32,
+    pub z: f32,
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            z: 2.0,
+            yaw: -std::f32::consts::FRAC_PI_2,
+            pitch: 0.0,
+        }
+    }
+}
+
+impl Camera {
+    pub fn position(&self) -> Vec3 {
+        Vec3::new(self.x, self.y, self.z)
+    }
+
+    pub fn front(&self) -> Vec3 {
+        Vec3::new(
+            self.yaw.cos() * self.pitch.cos(),
+            self.pitch.sin(),
+            self.yaw.sin() * self.pitch.cos(),
+        ).normalize()
+    }
+
+    pub fn view(&self) -> Mat4 {
+        let eye = self.position();
+        Mat4::look_at_rh(eye, eye + self.front(), Vec3::Y)
+    }
+
+    pub fn projection(&self, aspect: f32) -> Mat4 {
+        Mat4::perspective_rh(45.0f32.to_radians(), aspect, 0.1, 10.0)
+    }
+
+    /// Moves along the view direction; negative distances move backwards.
+    pub fn advance(&mut self, distance: f32) {
+        let front = self.front();
+        self.x += front.x * distance;
+        self.y += front.y * distance;
+        self.z += front.z * distance;
+    }
+
+    /// First-person look from a relative pointer delta: moving right turns
+    /// right and moving up looks up, unlike the drag-to-rotate mode.
+    pub fn look(&mut self, dx: f32, dy: f32) {
+        const SENSITIVITY: f32 = 0.002;
+        self.yaw += dx * SENSITIVITY;
+        self.pitch -= dy * SENSITIVITY;
+        self.pitch = self.pitch.clamp(-1.5, 1.5);
+    }
+}
@@ -0,0 +1,369 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::culling::{CullFace, Culling, FrontFace};
+use crate::gldebug::{DebugFilter, Severity};
+use crate::glx::{GlVersion, Profile, DEFAULT_VERSIONS, MIN_VERSION};
+use crate::headless::Backend;
+use crate::screenshot::{Format, ScreenshotOptions};
+use crate::pacing::{FramePacing, DEFAULT_FPS_CAP};
+use crate::recording::RecordOptions;
+use crate::render::ShapeKind;
+use crate::shading::ShadingModel;
+use crate::shadow::{CubePasses, ShadowSettings};
+
+const USAGE: &str = "\
+Usage: rust-test [OPTIONS]
+
+Options:
+  --fullscreen          Start fullscreen (toggle with F11)
+  --borderless          Start without window decorations (toggle with F10)
+  --size <W>x<H>        Window size, defaults to 3/4 of the screen
+  --capture-mouse       Start with the pointer grabbed for mouse-look (toggle with G)
+  --vsync               Sync buffer swaps to the display refresh (default)
+  --adaptive-vsync      VSync, but let late frames tear instead of stalling
+  --fps-cap <N>         Disable vsync and sleep to hold N frames per second
+  --uncapped            Disable vsync and render as fast as possible
+                        (V cycles vsync / capped / uncapped at runtime)
+  --msaa <N>            N-sample anti-aliasing through the window's visual,
+                        falling back to a multisampled framebuffer
+  --msaa-fbo <N>        N-sample anti-aliasing through a multisampled framebuffer
+  --srgb                Request an sRGB-capable framebuffer and encode output to sRGB
+  --gl-version <X.Y>    Request exactly this OpenGL version instead of the
+                        newest available from 4.6 down to 4.2, the oldest
+                        the shaders run on
+  --gl-profile <P>      Restrict to the 'core' or 'compat' profile
+  --gl-debug            Create a debug context and log KHR_debug messages
+  --gl-debug-level <L>  Lowest severity logged: notification, low (default),
+                        medium or high. Implies --gl-debug
+  --gl-debug-mute <N>   Drop messages from a source (api, window-system,
+                        shader-compiler, third-party, application) or of a
+                        type (error, deprecated, undefined-behavior,
+                        portability, performance, marker). Repeatable,
+                        implies --gl-debug
+  --headless            Render off-screen without a window or X display
+  --headless-backend <B>
+                        'egl' (surfaceless Mesa) or 'pbuffer' (GLX, needs an
+                        X display). Default: EGL, falling back to pbuffer
+  --frames <N>          Frames to render when headless (default 1)
+  --output <FILE>       Write the last headless frame to FILE (.png or .ppm)
+  --screenshot-dir <D>  Where F12 screenshots go (default: current directory)
+  --screenshot-format <F>
+                        'png' (default) or 'ppm'
+  --screenshot-alpha    Keep the framebuffer's alpha instead of saving opaque
+  --record              Record every frame from startup (toggle with F9). Frames
+                        use the screenshot format and alpha settings
+  --record-dir <D>      Where recordings go (default: recordings)
+  --record-fps <N>      Simulated frame rate of recordings (default 60); the
+                        scene advances 1/N seconds per frame however slow
+                        rendering is
+  --scene <FILE.ron>    Load the scene from FILE instead of the built-in demo,
+                        reloading it whenever it changes. F5 saves it back
+                        with the current camera
+  --shape <S>           Draw only shapes of one kind: 'cube', 'pyramid',
+                        'sphere' or 'cylinder'
+  --colour-mode <N>     Start in colour mode 0 (vertex colours, default) or
+                        1 (position colours); toggle with C
+  --cull <F>            Cull 'back', 'front' or 'both' faces, or 'none'
+                        (default; K cycles at runtime)
+  --front-face <W>      Winding of front faces: 'ccw' (default) or 'cw'
+  --show-back-faces     Hatch back faces in red (toggle with B)
+  --shading <M>         Lighting model: 'flat', 'gouraud', 'phong' (default)
+                        or 'blinn-phong'; H cycles at runtime
+  --attenuation         Dim point and spot lights with distance (toggle with J)
+  --no-shadows          Start without shadows (toggle with N)
+  --shadow-resolution <N>
+                        Width and height of each shadow map (default 1024)
+  --shadow-bias <B>     Depth bias against shadow acne (default 0.0005)
+  --shadow-pcf <N>      Soften shadow edges over a (2N+1)x(2N+1) square of
+                        texels, 0 to 4 (default 1)
+  --point-shadow-resolution <N>
+                        Size of each face of a point light's shadow cube
+                        (default 512)
+  --point-shadow-passes <N>
+                        Draw point shadow cubes in 1 pass with a geometry
+                        shader (default) or 6, one per face
+  --show-shadow-map     Show the first shadow map in a corner (M cycles
+                        through them)
+  -h, --help            Print this help";
+
+#[derive(Debug)]
+pub struct Options {
+    pub fullscreen: bool,
+    pub borderless: bool,
+    pub size: Option<(u16, u16)>,
+    pub capture_mouse: bool,
+    pub pacing: FramePacing,
+    pub fps_cap: u32,
+    pub msaa_samples: u32,
+    pub msaa_fbo: bool,
+    pub srgb: bool,
+    pub gl_version: Option<GlVersion>,
+    pub gl_profile: Option<Profile>,
+    pub gl_debug: bool,
+    pub gl_debug_filter: DebugFilter,
+    pub headless: bool,
+    pub headless_backend: Backend,
+    pub frames: u32,
+    pub output: Option<PathBuf>,
+    pub screenshot: ScreenshotOptions,
+    pub record: RecordOptions,
+    pub scene: Option<PathBuf>,
+    pub shape: Option<ShapeKind>,
+    pub colourmode: u32,
+    pub culling: Culling,
+    pub show_back_faces: bool,
+    pub shading: ShadingModel,
+    pub attenuation: bool,
+    pub shadows: bool,
+    pub shadow: ShadowSettings,
+    pub show_shadow_map: bool,
+}
+
+impl Default for Options {
+    fn default() -> Self {
+        Self {
+            fullscreen: false,
+            borderless: false,
+            size: None,
+            capture_mouse: false,
+            pacing: FramePacing::default(),
+            fps_cap: DEFAULT_FPS_CAP,
+            msaa_samples: 0,
+            msaa_fbo: false,
+            srgb: false,
+            gl_version: None,
+            gl_profile: None,
+            gl_debug: false,
+            gl_debug_filter: DebugFilter::default(),
+            headless: false,
+            headless_backend: Backend::Auto,
+            frames: 1,
+            output: None,
+            screenshot: ScreenshotOptions::default(),
+            record: RecordOptions::default(),
+            scene: None,
+            shape: None,
+            colourmode: 0,
+            culling: Culling::default(),
+            show_back_faces: false,
+            shading: ShadingModel::default(),
+            attenuation: false,
+            shadows: true,
+            shadow: ShadowSettings::default(),
+            show_shadow_map: false,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum CliError {
+    Help,
+    MissingValue(String),
+    InvalidValue(String, String),
+    Unknown(String),
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CliError::Help => write!(f, "{USAGE}"),
+            CliError::MissingValue(flag) => write!(f, "{flag} needs a value\n\n{USAGE}"),
+            CliError::InvalidValue(flag, value) => write!(f, "invalid value '{value}' for {flag}\n\n{USAGE}"),
+            CliError::Unknown(arg) => write!(f, "unknown argument '{arg}'\n\n{USAGE}"),
+        }
+    }
+}
+
+impl std::error::Error for CliError {}
+
+impl Options {
+    pub fn from_env() -> Result<Self, CliError> {
+        Self::parse(std::env::args().skip(1))
+    }
+
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, CliError> {
+        let mut opts = Options::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--fullscreen" => opts.fullscreen = true,
+                "--borderless" => opts.borderless = true,
+                "--capture-mouse" => opts.capture_mouse = true,
+                "--size" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.size = Some(parse_size(&value).ok_or(CliError::InvalidValue(arg, value))?);
+                }
+                "--vsync" => opts.pacing = FramePacing::VSync { adaptive: false },
+                "--adaptive-vsync" => opts.pacing = FramePacing::VSync { adaptive: true },
+                "--uncapped" => opts.pacing = FramePacing::Uncapped,
+                "--fps-cap" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let fps = value.parse().ok().filter(|&fps: &u32| fps > 0);
+                    opts.fps_cap = fps.ok_or(CliError::InvalidValue(arg, value))?;
+                    opts.pacing = FramePacing::Capped(opts.fps_cap);
+                }
+                "--srgb" => opts.srgb = true,
+                "--msaa" | "--msaa-fbo" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let samples = value.parse().ok().filter(|&n: &u32| n <= 32 && (n == 0 || n.is_power_of_two()));
+                    opts.msaa_fbo = arg == "--msaa-fbo";
+                    opts.msaa_samples = samples.ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--gl-version" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let version = parse_version(&value).filter(|&version| version >= MIN_VERSION);
+                    opts.gl_version = Some(version.ok_or(CliError::InvalidValue(arg, value))?);
+                }
+                "--gl-profile" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.gl_profile = Some(match value.as_str() {
+                        "core" => Profile::Core,
+                        "compat" | "compatibility" => Profile::Compatibility,
+                        _ => return Err(CliError::InvalidValue(arg, value)),
+                    });
+                }
+                "--gl-debug" => opts.gl_debug = true,
+                "--gl-debug-level" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.gl_debug_filter.min_severity = Severity::parse(&value).ok_or(CliError::InvalidValue(arg, value))?;
+                    opts.gl_debug = true;
+                }
+                "--gl-debug-mute" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    if !opts.gl_debug_filter.mute(&value) {
+                        return Err(CliError::InvalidValue(arg, value));
+                    }
+                    opts.gl_debug = true;
+                }
+                "--headless" => opts.headless = true,
+                "--headless-backend" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.headless_backend = match value.as_str() {
+                        "egl" => Backend::Egl,
+                        "pbuffer" => Backend::Pbuffer,
+                        _ => return Err(CliError::InvalidValue(arg, value)),
+                    };
+                    opts.headless = true;
+                }
+                "--frames" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let frames = value.parse().ok().filter(|&n: &u32| n > 0);
+                    opts.frames = frames.ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--output" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.output = Some(PathBuf::from(value));
+                }
+                "--screenshot-dir" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.screenshot.dir = PathBuf::from(value);
+                }
+                "--screenshot-format" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.screenshot.format = match value.as_str() {
+                        "png" => Format::Png,
+                        "ppm" => Format::Ppm,
+                        _ => return Err(CliError::InvalidValue(arg, value)),
+                    };
+                }
+                "--screenshot-alpha" => opts.screenshot.alpha = true,
+                "--record" => opts.record.start = true,
+                "--record-dir" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.record.dir = PathBuf::from(value);
+                }
+                "--record-fps" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let fps = value.parse().ok().filter(|&fps: &u32| fps > 0);
+                    opts.record.fps = fps.ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--scene" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.scene = Some(PathBuf::from(value));
+                }
+                "--shape" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.shape = Some(ShapeKind::parse(&value).ok_or(CliError::InvalidValue(arg, value))?);
+                }
+                "--colour-mode" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let mode = value.parse().ok().filter(|&mode: &u32| mode <= 1);
+                    opts.colourmode = mode.ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--cull" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.culling.face = CullFace::parse(&value).ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--front-face" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.culling.front_face = FrontFace::parse(&value).ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--show-back-faces" => opts.show_back_faces = true,
+                "--shading" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.shading = ShadingModel::parse(&value).ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--attenuation" => opts.attenuation = true,
+                "--no-shadows" => opts.shadows = false,
+                "--shadow-resolution" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let resolution = value.parse().ok().filter(|n: &u32| (16..=16384).contains(n));
+                    opts.shadow.resolution = resolution.ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--shadow-bias" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let bias = value.parse().ok().filter(|&b: &f32| (0.0..1.0).contains(&b));
+                    opts.shadow.bias = bias.ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--shadow-pcf" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let radius = value.parse().ok().filter(|&n: &u32| n <= 4);
+                    opts.shadow.pcf_radius = radius.ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--point-shadow-resolution" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    let resolution = value.parse().ok().filter(|n: &u32| (16..=16384).contains(n));
+                    opts.shadow.cube_resolution = resolution.ok_or(CliError::InvalidValue(arg, value))?;
+                }
+                "--point-shadow-passes" => {
+                    let value = args.next().ok_or_else(|| CliError::MissingValue(arg.clone()))?;
+                    opts.shadow.cube_passes = match value.as_str() {
+                        "1" => CubePasses::Single,
+                        "6" => CubePasses::Six,
+                        _ => return Err(CliError::InvalidValue(arg, value)),
+                    };
+                }
+                "--show-shadow-map" => opts.show_shadow_map = true,
+                "-h" | "--help" => return Err(CliError::Help),
+                _ => return Err(CliError::Unknown(arg)),
+            }
+        }
+
+        Ok(opts)
+    }
+}
+
+impl Options {
+    /// The context versions to try, narrowed by `--gl-version`/`--gl-profile`.
+    pub fn gl_versions(&self) -> Vec<(GlVersion, Profile)> {
+        match (self.gl_version, self.gl_profile) {
+            (Some(version), profile) => vec![(version, profile.unwrap_or(Profile::Core))],
+            (None, Some(profile)) => DEFAULT_VERSIONS.iter().copied().filter(|&(_, p)| p == profile).collect(),
+            (None, None) => DEFAULT_VERSIONS.to_vec(),
+        }
+    }
+}
+
+fn parse_version(value: &str) -> Option<GlVersion> {
+    let (major, minor) = value.split_once('.')?;
+    Some(GlVersion::new(major.parse().ok()?, minor.parse().ok()?))
+}
+
+fn parse_size(value: &str) -> Option<(u16, u16)> {
+    let (w, h) = value.split_once(['x', 'X'])?;
+    let w: u16 = w.parse().ok()?;
+    let h: u16 = h.parse().ok()?;
+    (w > 0 && h > 0).then_some((w, h))
+}
@@ -0,0 +1,98 @@
+use std::fmt;
+
+/// Which faces `GL_CULL_FACE` discards.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullFace {
+    /// Culling disabled, so both sides draw and depth testing sorts it out.
+    #[default]
+    None,
+    Back,
+    Front,
+    Both,
+}
+
+/// Which winding, as seen on screen, counts as a front face.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FrontFace {
+    /// What every mesh is normalized to, see `MeshData::normalize_winding`.
+    #[default]
+    Ccw,
+    Cw,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Culling {
+    pub face: CullFace,
+    pub front_face: FrontFace,
+}
+
+impl CullFace {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "none" => Some(CullFace::None),
+            "back" => Some(CullFace::Back),
+            "front" => Some(CullFace::Front),
+            "both" => Some(CullFace::Both),
+            _ => None,
+        }
+    }
+
+    /// The mode the runtime toggle switches to next. Skips `Both`, which
+    /// only ever draws an empty screen.
+    pub fn next(self) -> CullFace {
+        match self {
+            CullFace::None => CullFace::Back,
+            CullFace::Back => CullFace::Front,
+            CullFace::Front | CullFace::Both => CullFace::None,
+        }
+    }
+}
+
+impl FrontFace {
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "ccw" => Some(FrontFace::Ccw),
+            "cw" => Some(FrontFace::Cw),
+            _ => None,
+        }
+    }
+}
+
+impl Culling {
+    /// Sets the GL state; needs a current context.
+    pub fn apply(self) {
+        unsafe {
+            gl::FrontFace(match self.front_face {
+                FrontFace::Ccw => gl::CCW,
+                FrontFace::Cw => gl::CW,
+            });
+            let face = match self.face {
+                CullFace::None => {
+                    gl::Disable(gl::CULL_FACE);
+                    return;
+                }
+                CullFace::Back => gl::BACK,
+                CullFace::Front => gl::FRONT,
+                CullFace::Both => gl::FRONT_AND_BACK,
+            };
+            gl::Enable(gl::CULL_FACE);
+            gl::CullFace(face);
+        }
+    }
+}
+
+impl fmt::Display for Culling {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let face = match self.face {
+            CullFace::None => return write!(f, "culling off"),
+            CullFace::Back => "back",
+            CullFace::Front => "front",
+            CullFace::Both => "all",
+        };
+        let front = match self.front_face {
+            FrontFace::Ccw => "counter-clockwise",
+            FrontFace::Cw => "clockwise",
+        };
+        write!(f, "culling {face} faces ({front} is front)")
+    }
+}
@@ -0,0 +1,169 @@
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_uint, c_void};
+use std::ptr;
+
+use crate::glx::{self, GlContext, GlVersion, Profile};
+
+pub type EGLDisplay = *mut c_void;
+pub type EGLConfig = *mut c_void;
+pub type EGLContext = *mut c_void;
+pub type EGLSurface = *mut c_void;
+pub type EGLint = i32;
+pub type EGLenum = c_uint;
+pub type EGLBoolean = c_uint;
+
+pub const EGL_NONE: EGLint = 0x3038;
+pub const EGL_EXTENSIONS: EGLint = 0x3055;
+pub const EGL_OPENGL_API: EGLenum = 0x30A2;
+pub const EGL_PLATFORM_SURFACELESS_MESA: EGLenum = 0x31DD;
+pub const EGL_CONTEXT_MAJOR_VERSION: EGLint = 0x3098;
+pub const EGL_CONTEXT_MINOR_VERSION: EGLint = 0x30FB;
+pub const EGL_CONTEXT_OPENGL_PROFILE_MASK: EGLint = 0x30FD;
+pub const EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT: EGLint = 0x0001;
+pub const EGL_CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT: EGLint = 0x0002;
+pub const EGL_CONTEXT_OPENGL_DEBUG: EGLint = 0x31B0;
+pub const EGL_TRUE: EGLint = 1;
+pub const EGL_FALSE: EGLint = 0;
+
+#[link(name = "EGL")]
+unsafe extern "C" {
+    pub unsafe fn eglGetProcAddress(procname: *const c_char) -> *mut c_void;
+    pub unsafe fn eglGetError() -> EGLint;
+    pub unsafe fn eglQueryString(dpy: EGLDisplay, name: EGLint) -> *const c_char;
+    pub unsafe fn eglInitialize(dpy: EGLDisplay, major: *mut EGLint, minor: *mut EGLint) -> EGLBoolean;
+    pub unsafe fn eglTerminate(dpy: EGLDisplay) -> EGLBoolean;
+    pub unsafe fn eglBindAPI(api: EGLenum) -> EGLBoolean;
+    pub unsafe fn eglCreateContext(
+        dpy: EGLDisplay,
+        config: EGLConfig,
+        share_context: EGLContext,
+        attrib_list: *const EGLint,
+    ) -> EGLContext;
+    pub unsafe fn eglDestroyContext(dpy: EGLDisplay, ctx: EGLContext) -> EGLBoolean;
+    pub unsafe fn eglMakeCurrent(dpy: EGLDisplay, draw: EGLSurface, read: EGLSurface, ctx: EGLContext) -> EGLBoolean;
+}
+
+type GetPlatformDisplayFn = extern "C" fn(EGLenum, *mut c_void, *const EGLint) -> EGLDisplay;
+
+fn has_extension(dpy: EGLDisplay, name: &str) -> bool {
+    unsafe {
+        let exts = eglQueryString(dpy, EGL_EXTENSIONS);
+        !exts.is_null() && CStr::from_ptr(exts).to_string_lossy().split_whitespace().any(|ext| ext == name)
+    }
+}
+
+/// A GL context with no surface at all, on Mesa's surfaceless platform.
+/// Needs no X server and no GPU: on llvmpipe it runs anywhere Mesa does.
+/// There is no default framebuffer, so everything renders into FBOs.
+pub struct SurfacelessContext {
+    display: EGLDisplay,
+    pub context: GlContext,
+}
+
+impl SurfacelessContext {
+    /// Tries `versions` in order, like `glx::ContextBuilder`, and loads the
+    /// GL function pointers once a context is current.
+    pub fn new(versions: &[(GlVersion, Profile)], debug: bool) -> Result<Self, String> {
+        unsafe {
+            if !has_extension(ptr::null_mut(), "EGL_MESA_platform_surfaceless") {
+                return Err("EGL_MESA_platform_surfaceless unsupported".into());
+            }
+            let ptr = eglGetProcAddress(c"eglGetPlatformDisplayEXT".as_ptr() as _);
+            if ptr.is_null() {
+                return Err("eglGetPlatformDisplayEXT unavailable".into());
+            }
+            let get_platform_display: GetPlatformDisplayFn = std::mem::transmute(ptr);
+
+            let display = get_platform_display(EGL_PLATFORM_SURFACELESS_MESA, ptr::null_mut(), ptr::null());
+            let (mut major, mut minor) = (0, 0);
+            if display.is_null() || eglInitialize(display, &mut major, &mut minor) == 0 {
+                return Err(format!("eglInitialize failed (0x{:04X})", eglGetError()));
+            }
+            println!("EGL {major}.{minor} surfaceless display");
+
+            let result = Self::create_context(display, (major, minor), versions, debug);
+            if result.is_err() {
+                eglTerminate(display);
+            }
+            result
+        }
+    }
+
+    unsafe fn create_context(
+        display: EGLDisplay,
+        egl_version: (EGLint, EGLint),
+        versions: &[(GlVersion, Profile)],
+        debug: bool,
+    ) -> Result<Self, String> {
+        unsafe {
+            if !has_extension(display, "EGL_KHR_surfaceless_context") || !has_extension(display, "EGL_KHR_no_config_context") {
+                return Err("EGL display can't make configless, surfaceless contexts current".into());
+            }
+            if eglBindAPI(EGL_OPENGL_API) == 0 {
+                return Err("EGL has no desktop OpenGL".into());
+            }
+            // EGL_CONTEXT_OPENGL_DEBUG is new in 1.5; older displays reject
+            // any context that asks for it.
+            let debug_attrib = egl_version >= (1, 5);
+            if debug && !debug_attrib {
+                println!("EGL {}.{} can't request a debug context", egl_version.0, egl_version.1);
+            }
+
+            for &(version, profile) in versions {
+                let profile_bit = match profile {
+                    Profile::Core => EGL_CONTEXT_OPENGL_CORE_PROFILE_BIT,
+                    Profile::Compatibility => EGL_CONTEXT_OPENGL_COMPATIBILITY_PROFILE_BIT,
+                };
+                let mut ctx_attribs = vec![
+                    EGL_CONTEXT_MAJOR_VERSION, version.major as EGLint,
+                    EGL_CONTEXT_MINOR_VERSION, version.minor as EGLint,
+                    EGL_CONTEXT_OPENGL_PROFILE_MASK, profile_bit,
+                ];
+                if debug_attrib {
+                    ctx_attribs.extend_from_slice(&[EGL_CONTEXT_OPENGL_DEBUG, if debug { EGL_TRUE } else { EGL_FALSE }]);
+                }
+                ctx_attribs.push(EGL_NONE);
+
+                let ctx = eglCreateContext(display, ptr::null_mut(), ptr::null_mut(), ctx_attribs.as_ptr());
+                if ctx.is_null() {
+                    println!("OpenGL {version} {profile} context unavailable");
+                    continue;
+                }
+                if eglMakeCurrent(display, ptr::null_mut(), ptr::null_mut(), ctx) == 0 {
+                    eglDestroyContext(display, ctx);
+                    return Err(format!("eglMakeCurrent failed (0x{:04X})", eglGetError()));
+                }
+
+                init_gl_functions();
+                let (mut major, mut minor) = (0, 0);
+                gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+                gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+                let version = GlVersion::new(major as u32, minor as u32);
+                let profile = glx::current_profile(version, |name| eglGetProcAddress(name.as_ptr()));
+                let context = GlContext { raw: ctx, version, profile };
+                println!("OpenGL {} {} context current (EGL surfaceless)", context.version, context.profile);
+                return Ok(Self { display, context });
+            }
+
+            Err(format!("none of the requested OpenGL versions are available, and the shaders need {}", glx::MIN_VERSION))
+        }
+    }
+}
+
+impl Drop for SurfacelessContext {
+    fn drop(&mut self) {
+        unsafe {
+            eglMakeCurrent(self.display, ptr::null_mut(), ptr::null_mut(), ptr::null_mut());
+            eglDestroyContext(self.display, self.context.raw);
+            eglTerminate(self.display);
+        }
+    }
+}
+
+fn init_gl_functions() {
+    gl::load_with(|name| {
+        let c_name = CString::new(name).unwrap();
+        unsafe { eglGetProcAddress(c_name.as_ptr()) as *const _ }
+    });
+    glx::report_gl_info();
+}
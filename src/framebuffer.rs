@@ -0,0 +1,252 @@
+//! Framebuffer objects: colour and depth buffers to draw into off-screen,
+//! each either a texture to sample afterwards or a renderbuffer when it
+//! only needs to be blitted or read back.
+
+use gl::types::{GLenum, GLint, GLuint};
+
+use crate::gldebug;
+
+/// What backs an attachment.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Storage {
+    /// Can be sampled once drawn, to show it on a shape.
+    Texture,
+    /// Can only be blitted or read back.
+    Renderbuffer,
+}
+
+/// Which buffers a `Framebuffer` has and how they're stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FramebufferDesc {
+    /// RGBA8 colour, or `None` for depth only.
+    pub colour: Option<Storage>,
+    /// 24-bit depth, or `None` for colour only.
+    pub depth: Option<Storage>,
+    /// Samples per pixel; 0 and 1 both mean a plain single-sampled buffer.
+    pub samples: u32,
+}
+
+/// Colour and depth buffers sized together, reallocated by `resize`.
+pub struct Framebuffer {
+    fbo: GLuint,
+    desc: FramebufferDesc,
+    /// Texture or renderbuffer names, as `desc` says; 0 when absent.
+    colour: GLuint,
+    depth: GLuint,
+    width: u16,
+    height: u16,
+    label: String,
+}
+
+impl Framebuffer {
+    /// Needs a current context. `label` names it in errors and debug
+    /// output.
+    pub fn new(width: u16, height: u16, desc: FramebufferDesc, label: &str) -> Result<Self, String> {
+        let mut fbo = 0;
+        unsafe {
+            gl::GenFramebuffers(1, &mut fbo);
+        }
+        let mut framebuffer = Self {
+            fbo,
+            desc,
+            colour: 0,
+            depth: 0,
+            width: 0,
+            height: 0,
+            label: label.to_string(),
+        };
+        framebuffer.resize(width, height)?;
+        // Only once it's been bound is there an object to label.
+        gldebug::label(gl::FRAMEBUFFER, fbo, label);
+        Ok(framebuffer)
+    }
+
+    /// Reallocates the buffers at the new size, losing their contents; a
+    /// no-op if nothing changed. On failure the buffers are freed and the
+    /// size is 0x0, so the next `resize` tries again. Leaves the framebuffer
+    /// bindings as they were.
+    pub fn resize(&mut self, width: u16, height: u16) -> Result<(), String> {
+        if (width, height) == (self.width, self.height) {
+            return Ok(());
+        }
+        if width == 0 || height == 0 {
+            return Err(format!("{} can't be {width}x{height}", self.label));
+        }
+
+        let (mut read, mut draw): (GLint, GLint) = (0, 0);
+        unsafe {
+            self.delete_attachments();
+            (self.width, self.height) = (0, 0);
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read);
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut draw);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            match self.desc.colour {
+                Some(storage) => self.colour = self.attach(storage, gl::RGBA8, gl::COLOR_ATTACHMENT0, width, height),
+                None => {
+                    gl::DrawBuffer(gl::NONE);
+                    gl::ReadBuffer(gl::NONE);
+                }
+            }
+            if let Some(storage) = self.desc.depth {
+                self.depth = self.attach(storage, gl::DEPTH_COMPONENT24, gl::DEPTH_ATTACHMENT, width, height);
+            }
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read as GLuint);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, draw as GLuint);
+
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                self.delete_attachments();
+                return Err(format!("{} incomplete: {}", self.label, status_message(status)));
+            }
+        }
+        (self.width, self.height) = (width, height);
+        Ok(())
+    }
+
+    /// Allocates one buffer and attaches it to the bound framebuffer.
+    unsafe fn attach(&self, storage: Storage, format: GLenum, attachment: GLenum, width: u16, height: u16) -> GLuint {
+        let (width, height) = (width as i32, height as i32);
+        let samples = self.desc.samples as i32;
+        let mut id = 0;
+        unsafe {
+            match storage {
+                Storage::Texture if samples > 1 => {
+                    gl::GenTextures(1, &mut id);
+                    gl::BindTexture(gl::TEXTURE_2D_MULTISAMPLE, id);
+                    gl::TexStorage2DMultisample(gl::TEXTURE_2D_MULTISAMPLE, samples, format, width, height, gl::TRUE);
+                    gl::BindTexture(gl::TEXTURE_2D_MULTISAMPLE, 0);
+                    gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment, gl::TEXTURE_2D_MULTISAMPLE, id, 0);
+                }
+                Storage::Texture => {
+                    gl::GenTextures(1, &mut id);
+                    gl::BindTexture(gl::TEXTURE_2D, id);
+                    gl::TexStorage2D(gl::TEXTURE_2D, 1, format, width, height);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+                    gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+                    gl::BindTexture(gl::TEXTURE_2D, 0);
+                    gl::FramebufferTexture2D(gl::FRAMEBUFFER, attachment, gl::TEXTURE_2D, id, 0);
+                }
+                Storage::Renderbuffer => {
+                    gl::GenRenderbuffers(1, &mut id);
+                    gl::BindRenderbuffer(gl::RENDERBUFFER, id);
+                    gl::RenderbufferStorageMultisample(gl::RENDERBUFFER, samples.max(0), format, width, height);
+                    gl::BindRenderbuffer(gl::RENDERBUFFER, 0);
+                    gl::FramebufferRenderbuffer(gl::FRAMEBUFFER, attachment, gl::RENDERBUFFER, id);
+                }
+            }
+        }
+        id
+    }
+
+    unsafe fn delete_attachments(&mut self) {
+        let attachments = [(self.desc.colour, &mut self.colour), (self.desc.depth, &mut self.depth)];
+        for (storage, id) in attachments {
+            if *id == 0 {
+                continue;
+            }
+            unsafe {
+                match storage {
+                    Some(Storage::Texture) => gl::DeleteTextures(1, id),
+                    Some(Storage::Renderbuffer) => gl::DeleteRenderbuffers(1, id),
+                    None => {}
+                }
+            }
+            *id = 0;
+        }
+    }
+
+    pub fn id(&self) -> GLuint {
+        self.fbo
+    }
+
+    pub fn width(&self) -> u16 {
+        self.width
+    }
+
+    pub fn height(&self) -> u16 {
+        self.height
+    }
+
+    /// Directs drawing here. Setting the viewport to match is up to the
+    /// caller.
+    pub fn bind(&self) {
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+        }
+    }
+
+    /// Copies the colour buffer into the same-sized framebuffer `target`,
+    /// resolving any samples: 0 for the window, ready to swap. Leaves the
+    /// framebuffer bindings as they were.
+    pub fn blit_to(&self, target: GLuint) {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let (mut read, mut draw): (GLint, GLint) = (0, 0);
+        unsafe {
+            gl::GetIntegerv(gl::READ_FRAMEBUFFER_BINDING, &mut read);
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut draw);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, self.fbo);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, target);
+            gl::BlitFramebuffer(
+                0, 0, width, height,
+                0, 0, width, height,
+                gl::COLOR_BUFFER_BIT,
+                gl::NEAREST,
+            );
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, read as GLuint);
+            gl::BindFramebuffer(gl::DRAW_FRAMEBUFFER, draw as GLuint);
+        }
+    }
+
+    /// Binds the colour texture to texture unit `unit`, for a sampler set
+    /// to `unit`. Does nothing unless the colour is a single-sampled
+    /// texture.
+    pub fn bind_colour(&self, unit: u32) {
+        if self.desc.colour != Some(Storage::Texture) || self.desc.samples > 1 {
+            return;
+        }
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.colour);
+        }
+    }
+}
+
+impl Drop for Framebuffer {
+    fn drop(&mut self) {
+        unsafe {
+            self.delete_attachments();
+            gl::DeleteFramebuffers(1, &self.fbo);
+        }
+    }
+}
+
+/// Why `glCheckFramebufferStatus` said a framebuffer can't be drawn to,
+/// in words.
+pub fn status_message(status: GLenum) -> String {
+    let reason = match status {
+        gl::FRAMEBUFFER_UNDEFINED => "the default framebuffer doesn't exist",
+        gl::FRAMEBUFFER_INCOMPLETE_ATTACHMENT => "an attachment is incomplete or has no size",
+        gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT => "nothing is attached",
+        gl::FRAMEBUFFER_INCOMPLETE_DRAW_BUFFER => "a draw buffer has nothing attached",
+        gl::FRAMEBUFFER_INCOMPLETE_READ_BUFFER => "the read buffer has nothing attached",
+        gl::FRAMEBUFFER_UNSUPPORTED => "the driver doesn't support this combination of formats",
+        gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE => "the attachments have different sample counts",
+        gl::FRAMEBUFFER_INCOMPLETE_LAYER_TARGETS => "layered and unlayered attachments are mixed",
+        _ => "the status is unknown",
+    };
+    format!("{reason} (status 0x{status:04X})")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn incomplete_statuses_are_explained() {
+        assert_eq!(status_message(gl::FRAMEBUFFER_INCOMPLETE_MISSING_ATTACHMENT), "nothing is attached (status 0x8CD7)");
+        assert!(status_message(gl::FRAMEBUFFER_INCOMPLETE_MULTISAMPLE).contains("sample counts"));
+        assert!(status_message(0x1234).ends_with("(status 0x1234)"));
+    }
+}
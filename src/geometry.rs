@@ -0,0 +1,602 @@
+use std::collections::{HashMap, VecDeque};
+
+use glam::{Vec2, Vec3, Vec4};
+
+/// Half the side length of the built-in cube.
+pub const CUBE_HALF_EXTENT: f32 = 0.25;
+
+pub const SPHERE_COLOUR: Vec4 = Vec4::new(0.8, 0.2, 0.6, 1.0);
+
+pub const CYLINDER_COLOUR: Vec4 = Vec4::new(0.2, 0.6, 0.8, 1.0);
+
+/// Indexed triangle mesh in plain memory, ready to upload but with no GL
+/// in sight, so it can be generated and checked without a context.
+/// Triangles wind counter-clockwise seen from outside.
+#[derive(Debug, Clone, Default)]
+pub struct MeshData {
+    /// `w` is always 1, matching the shaders' `vec4 position`.
+    pub positions: Vec<Vec4>,
+    pub colours: Vec<Vec4>,
+    pub normals: Vec<Vec3>,
+    /// Texture coordinates, (0, 0) at the bottom left of the image.
+    pub uvs: Vec<Vec2>,
+    /// Unit vectors along increasing u, perpendicular to the normals, for
+    /// normal mapping. `w` is 1 or -1: the bitangent, along increasing v,
+    /// is `normal.cross(tangent) * w`. Filled in by `generate_tangents`.
+    pub tangents: Vec<Vec4>,
+    pub indices: Vec<u32>,
+}
+
+impl MeshData {
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [u32; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|t| [t[0], t[1], t[2]])
+    }
+
+    /// Maps each vertex to an id shared by every vertex at the same
+    /// position, so faces with their own copies of a corner (for flat
+    /// normals or colours) still count as connected.
+    pub fn welded_ids(&self) -> Vec<usize> {
+        let mut ids = HashMap::new();
+        self.positions
+            .iter()
+            .map(|p| {
+                let key = (p.truncate() * 1e4).round().as_ivec3().to_array();
+                let next = ids.len();
+                *ids.entry(key).or_insert(next)
+            })
+            .collect()
+    }
+
+    /// Rewinds triangles so every face is counter-clockwise seen from
+    /// outside, whatever order the data came in. Each connected piece is
+    /// first made consistent with its neighbours across shared edges, then
+    /// flipped as a whole if it encloses negative volume or, for an open
+    /// piece, if its faces mostly disagree with the vertex normals.
+    /// Returns how many triangles were flipped.
+    pub fn normalize_winding(&mut self) -> usize {
+        let ids = self.welded_ids();
+        let triangles: Vec<[usize; 3]> = self.triangles().map(|t| t.map(|i| ids[i as usize])).collect();
+
+        // Undirected edge -> (triangle, whether it runs low id to high id).
+        let mut edges: HashMap<(usize, usize), Vec<(usize, bool)>> = HashMap::new();
+        for (t, &[a, b, c]) in triangles.iter().enumerate() {
+            for (from, to) in [(a, b), (b, c), (c, a)] {
+                edges.entry((from.min(to), from.max(to))).or_default().push((t, from < to));
+            }
+        }
+
+        let mut flip: Vec<Option<bool>> = vec![None; triangles.len()];
+        let mut queue = VecDeque::new();
+        for seed in 0..triangles.len() {
+            if flip[seed].is_some() {
+                continue;
+            }
+            flip[seed] = Some(false);
+            queue.push_back(seed);
+            let mut component = vec![];
+            let mut closed = true;
+
+            while let Some(t) = queue.pop_front() {
+                component.push(t);
+                let [a, b, c] = triangles[t];
+                for (from, to) in [(a, b), (b, c), (c, a)] {
+                    let shared = &edges[&(from.min(to), from.max(to))];
+                    closed &= shared.len() == 2;
+                    for &(u, forward) in shared {
+                        if flip[u].is_none() {
+                            // Neighbours must cross the edge in opposite
+                            // directions, so running the same way means
+                            // one of the two has to turn over.
+                            flip[u] = Some(flip[t].unwrap() ^ (forward == (from < to)));
+                            queue.push_back(u);
+                        }
+                    }
+                }
+            }
+
+            let mut outward = 0.0;
+            for &t in &component {
+                let [a, b, c] = self.corners(t, flip[t].unwrap());
+                outward += if closed {
+                    // Six times the signed volume of the tetrahedron to the origin.
+                    a.dot(b.cross(c))
+                } else {
+                    let normals: Vec3 = self.indices[t * 3..t * 3 + 3].iter().map(|&i| self.normals[i as usize]).sum();
+                    (b - a).cross(c - a).dot(normals)
+                };
+            }
+            if outward < 0.0 {
+                for &t in &component {
+                    flip[t] = Some(!flip[t].unwrap());
+                }
+            }
+        }
+
+        let mut flipped = 0;
+        for (t, flip) in flip.into_iter().enumerate() {
+            if flip == Some(true) {
+                self.indices.swap(t * 3 + 1, t * 3 + 2);
+                flipped += 1;
+            }
+        }
+        flipped
+    }
+
+    /// Fills in `tangents` from the positions, normals and texture
+    /// coordinates, in the manner of MikkTSpace: each triangle's own
+    /// tangent and bitangent, the directions u and v grow in across it,
+    /// are averaged into its corners weighted by the angle there, then
+    /// made perpendicular to the vertex normal. Vertices where the
+    /// mapping collapses get an arbitrary tangent. Needs `uvs`.
+    pub fn generate_tangents(&mut self) {
+        assert_eq!(self.uvs.len(), self.vertex_count(), "tangents need texture coordinates");
+        let mut tangents = vec![Vec3::ZERO; self.vertex_count()];
+        let mut bitangents = vec![Vec3::ZERO; self.vertex_count()];
+        for triangle in self.triangles() {
+            let [p0, p1, p2] = triangle.map(|i| self.positions[i as usize].truncate());
+            let [uv0, uv1, uv2] = triangle.map(|i| self.uvs[i as usize]);
+            let (e1, e2) = (p1 - p0, p2 - p0);
+            let (d1, d2) = (uv1 - uv0, uv2 - uv0);
+            let det = d1.x * d2.y - d2.x * d1.y;
+            if det.abs() < 1e-12 {
+                continue;
+            }
+            // Solves e1 = d1.x T + d1.y B and e2 = d2.x T + d2.y B.
+            let tangent = ((e1 * d2.y - e2 * d1.y) / det).normalize_or_zero();
+            let bitangent = ((e2 * d1.x - e1 * d2.x) / det).normalize_or_zero();
+            let corners = [p0, p1, p2];
+            for k in 0..3 {
+                let here = corners[k];
+                let (a, b) = (corners[(k + 1) % 3] - here, corners[(k + 2) % 3] - here);
+                let angle = a.normalize_or_zero().dot(b.normalize_or_zero()).clamp(-1.0, 1.0).acos();
+                tangents[triangle[k] as usize] += tangent * angle;
+                bitangents[triangle[k] as usize] += bitangent * angle;
+            }
+        }
+        self.tangents = (0..self.vertex_count())
+            .map(|i| {
+                let normal = self.normals[i];
+                // Gram-Schmidt: drop the part along the normal.
+                let tangent = (tangents[i] - normal * normal.dot(tangents[i]))
+                    .try_normalize()
+                    .unwrap_or_else(|| normal.any_orthonormal_vector());
+                let handedness = if normal.cross(tangent).dot(bitangents[i]) < 0.0 { -1.0 } else { 1.0 };
+                tangent.extend(handedness)
+            })
+            .collect();
+    }
+
+    fn corners(&self, triangle: usize, flipped: bool) -> [Vec3; 3] {
+        let [a, mut b, mut c] = [0, 1, 2].map(|k| self.positions[self.indices[triangle * 3 + k] as usize].truncate());
+        if flipped {
+            std::mem::swap(&mut b, &mut c);
+        }
+        [a, b, c]
+    }
+
+    fn push_vertex(&mut self, position: Vec3, colour: Vec4, normal: Vec3, uv: Vec2) -> u32 {
+        self.positions.push(position.extend(1.0));
+        self.colours.push(colour);
+        self.normals.push(normal);
+        self.uvs.push(uv);
+        self.positions.len() as u32 - 1
+    }
+
+    /// Two triangles over four vertices given counter-clockwise from the
+    /// bottom left, with the whole texture stretched over them.
+    fn push_quad(&mut self, corners: [Vec3; 4], colour: Vec4, normal: Vec3) {
+        let uvs = [Vec2::ZERO, Vec2::X, Vec2::ONE, Vec2::Y];
+        let [a, b, c, d] = [0, 1, 2, 3].map(|i| self.push_vertex(corners[i], colour, normal, uvs[i]));
+        self.indices.extend([a, b, c, c, d, a]);
+    }
+}
+
+/// Axis-aligned cube with one flat colour per face. Faces don't share
+/// vertices, so each corner appears three times with different normals,
+/// and each face shows the whole texture, upright on the sides.
+pub fn cube() -> MeshData {
+    // (outward normal, u, v, colour) with u x v = normal, so walking
+    // -u-v, +u-v, +u+v, -u+v is counter-clockwise from outside.
+    let faces = [
+        (Vec3::NEG_Z, Vec3::NEG_X, Vec3::Y, Vec4::new(0.0, 0.0, 1.0, 1.0)),
+        (Vec3::X, Vec3::NEG_Z, Vec3::Y, Vec4::new(0.0, 1.0, 0.0, 1.0)),
+        (Vec3::Z, Vec3::X, Vec3::Y, Vec4::new(1.0, 1.0, 0.0, 1.0)),
+        (Vec3::NEG_X, Vec3::Z, Vec3::Y, Vec4::new(1.0, 0.0, 0.0, 1.0)),
+        (Vec3::NEG_Y, Vec3::X, Vec3::Z, Vec4::new(1.0, 0.0, 1.0, 1.0)),
+        (Vec3::Y, Vec3::Z, Vec3::X, Vec4::new(0.0, 1.0, 1.0, 1.0)),
+    ];
+
+    let mut mesh = MeshData::default();
+    for (normal, u, v, colour) in faces {
+        let centre = normal * CUBE_HALF_EXTENT;
+        let (u, v) = (u * CUBE_HALF_EXTENT, v * CUBE_HALF_EXTENT);
+        mesh.push_quad([centre - u - v, centre + u - v, centre + u + v, centre - u + v], colour, normal);
+    }
+    mesh
+}
+
+/// Square pyramid standing on the y = 0 plane with its apex at y = 0.5.
+/// A grey base and one colour per side, all flat shaded. Each side maps
+/// the bottom edge of the texture along its base, peaking mid-top.
+pub fn pyramid() -> MeshData {
+    let half = CUBE_HALF_EXTENT;
+    let apex = Vec3::new(0.0, 0.5, 0.0);
+    // Base corners going clockwise seen from above, which makes each
+    // (corner, next corner, apex) triangle counter-clockwise from outside.
+    let corners = [
+        Vec3::new(-half, 0.0, -half),
+        Vec3::new(-half, 0.0, half),
+        Vec3::new(half, 0.0, half),
+        Vec3::new(half, 0.0, -half),
+    ];
+    let side_colours = [
+        Vec4::new(1.0, 1.0, 0.0, 1.0),
+        Vec4::new(0.0, 0.0, 1.0, 1.0),
+        Vec4::new(0.0, 1.0, 0.0, 1.0),
+        Vec4::new(1.0, 0.0, 0.0, 1.0),
+    ];
+
+    let mut mesh = MeshData::default();
+    let [c0, c1, c2, c3] = corners;
+    mesh.push_quad([c0, c3, c2, c1], Vec4::new(0.5, 0.5, 0.5, 1.0), Vec3::NEG_Y);
+
+    for (i, colour) in side_colours.into_iter().enumerate() {
+        let (a, b) = (corners[i], corners[(i + 1) % 4]);
+        let normal = (b - a).cross(apex - a).normalize();
+        let uvs = [Vec2::ZERO, Vec2::X, Vec2::new(0.5, 1.0)];
+        let indices = [(a, uvs[0]), (b, uvs[1]), (apex, uvs[2])].map(|(p, uv)| mesh.push_vertex(p, colour, normal, uv));
+        mesh.indices.extend(indices);
+    }
+    mesh
+}
+
+/// Unit UV sphere around the z axis, mapped equirectangularly: u runs
+/// once round with the longitude and v from the south pole (0) to the
+/// north (1). Each pole has a vertex per longitude and each ring an extra
+/// one on the seam, so the texture wraps without smearing; everywhere else
+/// neighbouring triangles share vertices. Normals are the positions
+/// themselves.
+pub fn sphere(num_lats: usize, num_longs: usize, colour: Vec4) -> MeshData {
+    assert!(num_lats >= 2 && num_longs >= 3, "a sphere needs at least 2 latitudes and 3 longitudes");
+    let mut mesh = MeshData::default();
+
+    // Each pole vertex sits mid-way along its triangle's texture span.
+    let pole_u = |j: usize| (j as f32 + 0.5) / num_longs as f32;
+    for j in 0..num_longs {
+        mesh.push_vertex(Vec3::Z, colour, Vec3::Z, Vec2::new(pole_u(j), 1.0));
+    }
+
+    let lat_step = std::f32::consts::PI / num_lats as f32;
+    let long_step = 2.0 * std::f32::consts::PI / num_longs as f32;
+    for i in 1..num_lats {
+        let (sin_lat, cos_lat) = (i as f32 * lat_step).sin_cos();
+        let v = 1.0 - i as f32 / num_lats as f32;
+        for j in 0..=num_longs {
+            let (sin_long, cos_long) = (j as f32 * long_step).sin_cos();
+            let position = Vec3::new(cos_long * sin_lat, sin_long * sin_lat, cos_lat);
+            mesh.push_vertex(position, colour, position, Vec2::new(j as f32 / num_longs as f32, v));
+        }
+    }
+
+    let south = mesh.vertex_count() as u32;
+    for j in 0..num_longs {
+        mesh.push_vertex(Vec3::NEG_Z, colour, Vec3::NEG_Z, Vec2::new(pole_u(j), 0.0));
+    }
+
+    // Index of the vertex on ring `ring` (0 is nearest the north pole) at
+    // longitude `j`, which runs to num_longs inclusive for the seam.
+    let ring = |ring: usize, j: usize| (num_longs + ring * (num_longs + 1) + j) as u32;
+
+    for j in 0..num_longs {
+        mesh.indices.extend([j as u32, ring(0, j), ring(0, j + 1)]);
+    }
+
+    // num_lats - 1 rings make num_lats - 2 bands between them.
+    for lat in 0..num_lats - 2 {
+        for j in 0..num_longs {
+            let (i1, i2) = (ring(lat, j), ring(lat, j + 1));
+            let (i3, i4) = (ring(lat + 1, j), ring(lat + 1, j + 1));
+            mesh.indices.extend([i1, i3, i2, i3, i4, i2]);
+        }
+    }
+
+    let last = num_lats - 2;
+    for j in 0..num_longs {
+        mesh.indices.extend([ring(last, j), south + j as u32, ring(last, j + 1)]);
+    }
+
+    mesh
+}
+
+/// Capped cylinder along the y axis, centred on the origin. The side
+/// shares its vertices round each rim for smooth normals, with an extra
+/// pair on the seam so the texture wraps once round; the caps get their
+/// own copies facing straight up and down, mapped straight down onto the
+/// middle of the texture.
+pub fn cylinder(segments: usize, radius: f32, height: f32, colour: Vec4) -> MeshData {
+    assert!(segments >= 3, "a cylinder needs at least 3 segments");
+    let mut mesh = MeshData::default();
+    let half = height / 2.0;
+    let step = 2.0 * std::f32::consts::PI / segments as f32;
+    let rim = |j: usize| {
+        let (sin, cos) = (j as f32 * step).sin_cos();
+        Vec3::new(cos, 0.0, sin)
+    };
+
+    // Side: bottom rim at 2j, top rim at 2j + 1. The rim runs clockwise
+    // seen from above, so u goes the other way to read left to right from
+    // outside.
+    for j in 0..=segments {
+        let normal = rim(j);
+        let u = 1.0 - j as f32 / segments as f32;
+        mesh.push_vertex(normal * radius - Vec3::Y * half, colour, normal, Vec2::new(u, 0.0));
+        mesh.push_vertex(normal * radius + Vec3::Y * half, colour, normal, Vec2::new(u, 1.0));
+    }
+    for j in 0..segments as u32 {
+        let (b0, t0, b1, t1) = (2 * j, 2 * j + 1, 2 * j + 2, 2 * j + 3);
+        mesh.indices.extend([b0, t0, t1, t1, b1, b0]);
+    }
+
+    let cap_uv = |p: Vec3| Vec2::new(0.5 + p.x * 0.5, 0.5 - p.z * 0.5);
+    for (y, normal) in [(half, Vec3::Y), (-half, Vec3::NEG_Y)] {
+        let centre = mesh.push_vertex(Vec3::Y * y, colour, normal, cap_uv(Vec3::ZERO));
+        for j in 0..segments {
+            mesh.push_vertex(rim(j) * radius + Vec3::Y * y, colour, normal, cap_uv(rim(j)));
+        }
+        for j in 0..segments {
+            let (a, b) = (centre + 1 + j as u32, centre + 1 + ((j + 1) % segments) as u32);
+            // The rim runs clockwise seen from above.
+            if y > 0.0 {
+                mesh.indices.extend([centre, b, a]);
+            } else {
+                mesh.indices.extend([centre, a, b]);
+            }
+        }
+    }
+    mesh
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn centroid(mesh: &MeshData) -> Vec3 {
+        mesh.positions.iter().map(|p| p.truncate()).sum::<Vec3>() / mesh.vertex_count() as f32
+    }
+
+    fn face_normal(mesh: &MeshData, [a, b, c]: [u32; 3]) -> Vec3 {
+        let [a, b, c] = [a, b, c].map(|i| mesh.positions[i as usize].truncate());
+        (b - a).cross(c - a)
+    }
+
+    fn assert_well_formed(name: &str, mesh: &MeshData) {
+        let n = mesh.vertex_count();
+        assert_eq!(mesh.colours.len(), n, "{name}: colour count");
+        assert_eq!(mesh.normals.len(), n, "{name}: normal count");
+        assert_eq!(mesh.uvs.len(), n, "{name}: uv count");
+        assert!(mesh.uvs.iter().all(|uv| uv.cmpge(Vec2::ZERO).all() && uv.cmple(Vec2::ONE).all()), "{name}: uv outside the texture");
+        assert_eq!(mesh.indices.len() % 3, 0, "{name}: index count isn't a multiple of 3");
+        assert!(mesh.indices.iter().all(|&i| (i as usize) < n), "{name}: index out of range");
+        assert!(mesh.positions.iter().all(|p| p.w == 1.0), "{name}: position with w != 1");
+        for normal in &mesh.normals {
+            assert!((normal.length() - 1.0).abs() < 1e-5, "{name}: normal {normal} isn't unit length");
+        }
+        for tri in mesh.triangles() {
+            assert!(face_normal(mesh, tri).length() > 1e-8, "{name}: degenerate triangle {tri:?}");
+        }
+    }
+
+    /// Every edge is shared by exactly two triangles that traverse it in
+    /// opposite directions, and the surface is a topological sphere.
+    fn assert_closed_and_consistent(name: &str, mesh: &MeshData) {
+        let ids = mesh.welded_ids();
+        let mut edges: HashMap<(usize, usize), u32> = HashMap::new();
+        for tri in mesh.triangles() {
+            let [a, b, c] = tri.map(|i| ids[i as usize]);
+            for edge in [(a, b), (b, c), (c, a)] {
+                *edges.entry(edge).or_default() += 1;
+            }
+        }
+        for (&(a, b), &count) in &edges {
+            assert_eq!(count, 1, "{name}: edge {a}->{b} used by {count} triangles in the same direction");
+            assert!(edges.contains_key(&(b, a)), "{name}: edge {a}->{b} is on a hole");
+        }
+
+        let vertices = ids.iter().max().map_or(0, |&max| max + 1) as i64;
+        let euler = vertices - (edges.len() / 2) as i64 + (mesh.indices.len() / 3) as i64;
+        assert_eq!(euler, 2, "{name}: Euler characteristic {euler}, expected a sphere's");
+    }
+
+    /// Faces wind counter-clockwise seen from outside and agree with their
+    /// vertex normals. Only meaningful for convex shapes.
+    fn assert_outward(name: &str, mesh: &MeshData) {
+        let centre = centroid(mesh);
+        for tri in mesh.triangles() {
+            let normal = face_normal(mesh, tri);
+            let [a, b, c] = tri.map(|i| mesh.positions[i as usize].truncate());
+            let mid = (a + b + c) / 3.0;
+            assert!(normal.dot(mid - centre) > 0.0, "{name}: triangle {tri:?} winds inwards");
+            for i in tri {
+                let vertex_normal = mesh.normals[i as usize];
+                assert!(vertex_normal.dot(normal) > 0.0, "{name}: vertex {i} normal disagrees with its face");
+                let position = mesh.positions[i as usize].truncate();
+                assert!(vertex_normal.dot(position - centre) > 0.0, "{name}: vertex {i} normal points inwards");
+            }
+        }
+    }
+
+    #[test]
+    fn cube_counts() {
+        let mesh = cube();
+        assert_eq!(mesh.vertex_count(), 24);
+        assert_eq!(mesh.indices.len(), 36);
+    }
+
+    #[test]
+    fn cube_is_closed_and_outward() {
+        let mesh = cube();
+        assert_well_formed("cube", &mesh);
+        assert_closed_and_consistent("cube", &mesh);
+        assert_outward("cube", &mesh);
+        for p in &mesh.positions {
+            assert_eq!(p.truncate().abs(), Vec3::splat(CUBE_HALF_EXTENT), "corner {p} off the cube");
+        }
+    }
+
+    #[test]
+    fn pyramid_counts() {
+        let mesh = pyramid();
+        assert_eq!(mesh.vertex_count(), 16);
+        assert_eq!(mesh.indices.len(), 18);
+    }
+
+    #[test]
+    fn pyramid_is_closed_and_outward() {
+        let mesh = pyramid();
+        assert_well_formed("pyramid", &mesh);
+        assert_closed_and_consistent("pyramid", &mesh);
+        assert_outward("pyramid", &mesh);
+    }
+
+    #[test]
+    fn sphere_counts() {
+        let mesh = sphere(80, 80, SPHERE_COLOUR);
+        assert_eq!(mesh.vertex_count(), 2 * 80 + 79 * 81);
+        assert_eq!(mesh.indices.len(), 6 * 80 * 79);
+    }
+
+    #[test]
+    fn sphere_properties_hold_at_every_resolution() {
+        for num_lats in 2..=16 {
+            for num_longs in 3..=16 {
+                let name = format!("sphere {num_lats}x{num_longs}");
+                let mesh = sphere(num_lats, num_longs, SPHERE_COLOUR);
+                assert_eq!(mesh.vertex_count(), 2 * num_longs + (num_lats - 1) * (num_longs + 1), "{name}: vertex count");
+                assert_eq!(mesh.indices.len(), 6 * num_longs * (num_lats - 1), "{name}: index count");
+                assert_well_formed(&name, &mesh);
+                assert_closed_and_consistent(&name, &mesh);
+                assert_outward(&name, &mesh);
+
+                for (p, n) in mesh.positions.iter().zip(&mesh.normals) {
+                    assert!((p.truncate().length() - 1.0).abs() < 1e-5, "{name}: {p} isn't on the unit sphere");
+                    assert!(p.truncate().distance(*n) < 1e-5, "{name}: normal at {p} isn't radial");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn sphere_uvs_are_equirectangular_without_wrapping_triangles() {
+        use std::f32::consts::{PI, TAU};
+        let (num_lats, num_longs) = (8, 12);
+        let mesh = sphere(num_lats, num_longs, SPHERE_COLOUR);
+        for (p, uv) in mesh.positions.iter().zip(&mesh.uvs) {
+            let p = p.truncate();
+            assert!((uv.y - (1.0 - p.z.clamp(-1.0, 1.0).acos() / PI)).abs() < 1e-5, "v at {p} is {}", uv.y);
+            if p.truncate().length() > 1e-4 {
+                let long = p.y.atan2(p.x).rem_euclid(TAU) / TAU;
+                let seam = uv.x == 1.0 && long < 1e-5;
+                assert!((uv.x - long).abs() < 1e-5 || seam, "u at {p} is {}", uv.x);
+            }
+        }
+        for tri in mesh.triangles() {
+            let us = tri.map(|i| mesh.uvs[i as usize].x);
+            let span = us.iter().copied().fold(f32::MIN, f32::max) - us.iter().copied().fold(f32::MAX, f32::min);
+            assert!(span <= 1.0 / num_longs as f32 + 1e-5, "triangle {tri:?} spans u {us:?}");
+        }
+    }
+
+    #[test]
+    fn cylinder_properties_hold_at_every_resolution() {
+        for segments in 3..=24 {
+            let name = format!("cylinder {segments}");
+            let mesh = cylinder(segments, 0.3, 0.8, CYLINDER_COLOUR);
+            assert_eq!(mesh.vertex_count(), 4 * segments + 4, "{name}: vertex count");
+            assert_eq!(mesh.indices.len(), 12 * segments, "{name}: index count");
+            assert_well_formed(&name, &mesh);
+            assert_closed_and_consistent(&name, &mesh);
+            assert_outward(&name, &mesh);
+            for p in &mesh.positions {
+                assert!(p.y.abs() <= 0.4 + 1e-6 && p.truncate().with_y(0.0).length() <= 0.3 + 1e-6, "{name}: {p} outside");
+            }
+        }
+    }
+
+    #[test]
+    fn tangents_follow_the_texture_on_every_shape() {
+        let shapes = [
+            ("cube", cube()),
+            ("pyramid", pyramid()),
+            ("sphere", sphere(12, 16, SPHERE_COLOUR)),
+            ("cylinder", cylinder(16, 0.5, 1.0, CYLINDER_COLOUR)),
+        ];
+        for (name, mut mesh) in shapes {
+            mesh.generate_tangents();
+            assert_eq!(mesh.tangents.len(), mesh.vertex_count(), "{name}");
+            for (tangent, normal) in mesh.tangents.iter().zip(&mesh.normals) {
+                let t = tangent.truncate();
+                assert!((t.length() - 1.0).abs() < 1e-4 && t.dot(*normal).abs() < 1e-4, "{name}: {tangent} at {normal}");
+                assert!(tangent.w.abs() == 1.0, "{name}");
+            }
+            // Tangent and bitangent point where u and v grow across
+            // every triangle.
+            for triangle in mesh.triangles() {
+                let [p0, p1, p2] = triangle.map(|i| mesh.positions[i as usize].truncate());
+                let [uv0, uv1, uv2] = triangle.map(|i| mesh.uvs[i as usize]);
+                let (d1, d2) = (uv1 - uv0, uv2 - uv0);
+                let det = d1.x * d2.y - d2.x * d1.y;
+                let du = ((p1 - p0) * d2.y - (p2 - p0) * d1.y) / det;
+                let dv = ((p2 - p0) * d1.x - (p1 - p0) * d2.x) / det;
+                for i in triangle.map(|i| i as usize) {
+                    let tangent = mesh.tangents[i];
+                    let bitangent = mesh.normals[i].cross(tangent.truncate()) * tangent.w;
+                    assert!(tangent.truncate().dot(du) > 0.0 && bitangent.dot(dv) > 0.0, "{name}: triangle {triangle:?}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn built_in_shapes_are_already_normalized() {
+        assert_eq!(cube().normalize_winding(), 0);
+        assert_eq!(pyramid().normalize_winding(), 0);
+        assert_eq!(sphere(12, 12, SPHERE_COLOUR).normalize_winding(), 0);
+        assert_eq!(cylinder(12, 0.5, 1.0, CYLINDER_COLOUR).normalize_winding(), 0);
+    }
+
+    #[test]
+    fn normalize_winding_repairs_mixed_triangles() {
+        for (name, original) in [("cube", cube()), ("pyramid", pyramid()), ("sphere", sphere(6, 7, SPHERE_COLOUR))] {
+            let mut mesh = original.clone();
+            let triangles = mesh.indices.len() / 3;
+            let scrambled: Vec<usize> = (0..triangles).filter(|t| t % 3 != 1).collect();
+            for &t in &scrambled {
+                mesh.indices.swap(t * 3 + 1, t * 3 + 2);
+            }
+            assert_eq!(mesh.normalize_winding(), scrambled.len(), "{name}");
+            assert_outward(name, &mesh);
+        }
+    }
+
+    #[test]
+    fn normalize_winding_orients_open_meshes_by_their_normals() {
+        let mut quad = MeshData::default();
+        let corners = [Vec3::ZERO, Vec3::X, Vec3::new(1.0, 1.0, 0.0), Vec3::Y];
+        // Clockwise seen from +z, where the normals point.
+        quad.push_quad([corners[0], corners[3], corners[2], corners[1]], Vec4::ONE, Vec3::Z);
+        assert_eq!(quad.normalize_winding(), 2);
+        for tri in quad.triangles() {
+            assert!(face_normal(&quad, tri).z > 0.0);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "at least 2 latitudes")]
+    fn sphere_rejects_a_single_latitude() {
+        sphere(1, 8, SPHERE_COLOUR);
+    }
+}
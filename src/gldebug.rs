@@ -0,0 +1,261 @@
+use std::ffi::CStr;
+use std::os::raw::c_void;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use gl::types::{GLchar, GLenum, GLsizei, GLuint};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Api,
+    WindowSystem,
+    ShaderCompiler,
+    ThirdParty,
+    Application,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MessageType {
+    Error,
+    DeprecatedBehavior,
+    UndefinedBehavior,
+    Portability,
+    Performance,
+    Marker,
+    Other,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    Notification,
+    Low,
+    Medium,
+    High,
+}
+
+impl Source {
+    const ALL: [Source; 6] = [
+        Source::Api,
+        Source::WindowSystem,
+        Source::ShaderCompiler,
+        Source::ThirdParty,
+        Source::Application,
+        Source::Other,
+    ];
+
+    fn to_gl(self) -> GLenum {
+        match self {
+            Source::Api => gl::DEBUG_SOURCE_API,
+            Source::WindowSystem => gl::DEBUG_SOURCE_WINDOW_SYSTEM,
+            Source::ShaderCompiler => gl::DEBUG_SOURCE_SHADER_COMPILER,
+            Source::ThirdParty => gl::DEBUG_SOURCE_THIRD_PARTY,
+            Source::Application => gl::DEBUG_SOURCE_APPLICATION,
+            Source::Other => gl::DEBUG_SOURCE_OTHER,
+        }
+    }
+
+    fn from_gl(source: GLenum) -> Self {
+        Self::ALL.into_iter().find(|s| s.to_gl() == source).unwrap_or(Source::Other)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Source::Api => "api",
+            Source::WindowSystem => "window-system",
+            Source::ShaderCompiler => "shader-compiler",
+            Source::ThirdParty => "third-party",
+            Source::Application => "application",
+            Source::Other => "other-source",
+        }
+    }
+}
+
+impl MessageType {
+    const ALL: [MessageType; 7] = [
+        MessageType::Error,
+        MessageType::DeprecatedBehavior,
+        MessageType::UndefinedBehavior,
+        MessageType::Portability,
+        MessageType::Performance,
+        MessageType::Marker,
+        MessageType::Other,
+    ];
+
+    fn to_gl(self) -> GLenum {
+        match self {
+            MessageType::Error => gl::DEBUG_TYPE_ERROR,
+            MessageType::DeprecatedBehavior => gl::DEBUG_TYPE_DEPRECATED_BEHAVIOR,
+            MessageType::UndefinedBehavior => gl::DEBUG_TYPE_UNDEFINED_BEHAVIOR,
+            MessageType::Portability => gl::DEBUG_TYPE_PORTABILITY,
+            MessageType::Performance => gl::DEBUG_TYPE_PERFORMANCE,
+            MessageType::Marker => gl::DEBUG_TYPE_MARKER,
+            MessageType::Other => gl::DEBUG_TYPE_OTHER,
+        }
+    }
+
+    fn from_gl(gltype: GLenum) -> Self {
+        Self::ALL.into_iter().find(|t| t.to_gl() == gltype).unwrap_or(MessageType::Other)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            MessageType::Error => "error",
+            MessageType::DeprecatedBehavior => "deprecated",
+            MessageType::UndefinedBehavior => "undefined-behavior",
+            MessageType::Portability => "portability",
+            MessageType::Performance => "performance",
+            MessageType::Marker => "marker",
+            MessageType::Other => "other-type",
+        }
+    }
+}
+
+impl Severity {
+    const ALL: [Severity; 4] = [Severity::Notification, Severity::Low, Severity::Medium, Severity::High];
+
+    fn to_gl(self) -> GLenum {
+        match self {
+            Severity::Notification => gl::DEBUG_SEVERITY_NOTIFICATION,
+            Severity::Low => gl::DEBUG_SEVERITY_LOW,
+            Severity::Medium => gl::DEBUG_SEVERITY_MEDIUM,
+            Severity::High => gl::DEBUG_SEVERITY_HIGH,
+        }
+    }
+
+    fn from_gl(severity: GLenum) -> Self {
+        Self::ALL.into_iter().find(|s| s.to_gl() == severity).unwrap_or(Severity::High)
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        match name {
+            "notification" => Some(Severity::Notification),
+            "low" => Some(Severity::Low),
+            "medium" => Some(Severity::Medium),
+            "high" => Some(Severity::High),
+            _ => None,
+        }
+    }
+}
+
+/// Which debug messages reach the log. Filtering is done by the driver
+/// through `glDebugMessageControl`, so muted messages cost nothing.
+#[derive(Debug, Clone)]
+pub struct DebugFilter {
+    pub min_severity: Severity,
+    pub muted_sources: Vec<Source>,
+    pub muted_types: Vec<MessageType>,
+}
+
+impl Default for DebugFilter {
+    fn default() -> Self {
+        Self {
+            min_severity: Severity::Low,
+            muted_sources: Vec::new(),
+            muted_types: Vec::new(),
+        }
+    }
+}
+
+impl DebugFilter {
+    /// Mutes a source or message type by the name used in log lines.
+    pub fn mute(&mut self, name: &str) -> bool {
+        if let Some(source) = Source::ALL.into_iter().find(|s| s.name() == name) {
+            self.muted_sources.push(source);
+        } else if let Some(gltype) = MessageType::ALL.into_iter().find(|t| t.name() == name) {
+            self.muted_types.push(gltype);
+        } else {
+            return false;
+        }
+        true
+    }
+}
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+fn supported() -> bool {
+    unsafe {
+        let (mut major, mut minor) = (0, 0);
+        gl::GetIntegerv(gl::MAJOR_VERSION, &mut major);
+        gl::GetIntegerv(gl::MINOR_VERSION, &mut minor);
+        if (major, minor) >= (4, 3) {
+            return true;
+        }
+
+        let mut count = 0;
+        gl::GetIntegerv(gl::NUM_EXTENSIONS, &mut count);
+        (0..count as GLuint).any(|i| {
+            let ext = gl::GetStringi(gl::EXTENSIONS, i);
+            !ext.is_null() && CStr::from_ptr(ext as *const GLchar).to_bytes() == b"GL_KHR_debug"
+        })
+    }
+}
+
+extern "system" fn log_message(
+    source: GLenum,
+    gltype: GLenum,
+    id: GLuint,
+    severity: GLenum,
+    _length: GLsizei,
+    message: *const GLchar,
+    _user_param: *mut c_void,
+) {
+    let message = unsafe { CStr::from_ptr(message) }.to_string_lossy();
+    let source = Source::from_gl(source).name();
+    let gltype = MessageType::from_gl(gltype).name();
+    match Severity::from_gl(severity) {
+        Severity::High => eprintln!("[GL error] {source}/{gltype} #{id}: {message}"),
+        Severity::Medium => eprintln!("[GL warning] {source}/{gltype} #{id}: {message}"),
+        Severity::Low => println!("[GL info] {source}/{gltype} #{id}: {message}"),
+        Severity::Notification => println!("[GL note] {source}/{gltype} #{id}: {message}"),
+    }
+}
+
+/// Installs the message callback. Output is synchronous so a message is
+/// logged from inside the call that caused it, which makes a breakpoint on
+/// `log_message` land on the culprit. Needs GL 4.3 or `KHR_debug`.
+pub fn install(filter: &DebugFilter) -> Result<(), String> {
+    if !supported() {
+        return Err("glDebugMessageCallback unavailable (needs GL 4.3 or KHR_debug)".into());
+    }
+
+    unsafe {
+        let mut flags = 0;
+        gl::GetIntegerv(gl::CONTEXT_FLAGS, &mut flags);
+        if flags as GLenum & gl::CONTEXT_FLAG_DEBUG_BIT == 0 {
+            println!("Context has no debug flag, the driver may report little");
+        }
+
+        gl::Enable(gl::DEBUG_OUTPUT);
+        gl::Enable(gl::DEBUG_OUTPUT_SYNCHRONOUS);
+        gl::DebugMessageCallback(Some(log_message), std::ptr::null());
+
+        let control = |source, gltype, severity, enabled| {
+            gl::DebugMessageControl(source, gltype, severity, 0, std::ptr::null(), enabled);
+        };
+        control(gl::DONT_CARE, gl::DONT_CARE, gl::DONT_CARE, gl::TRUE);
+        for severity in Severity::ALL.into_iter().filter(|&s| s < filter.min_severity) {
+            control(gl::DONT_CARE, gl::DONT_CARE, severity.to_gl(), gl::FALSE);
+        }
+        for source in &filter.muted_sources {
+            control(source.to_gl(), gl::DONT_CARE, gl::DONT_CARE, gl::FALSE);
+        }
+        for gltype in &filter.muted_types {
+            control(gl::DONT_CARE, gltype.to_gl(), gl::DONT_CARE, gl::FALSE);
+        }
+    }
+
+    ENABLED.store(true, Ordering::Relaxed);
+    println!("GL debug output enabled");
+    Ok(())
+}
+
+/// Names a GL object so debug messages refer to it by `name`. A no-op
+/// unless debug output was installed.
+pub fn label(identifier: GLenum, object: GLuint, name: &str) {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return;
+    }
+    unsafe {
+        gl::ObjectLabel(identifier, object, name.len() as GLsizei, name.as_ptr() as *const GLchar);
+    }
+}
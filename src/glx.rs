@@ -1,4 +1,6 @@
-use std::{ffi::{CStr, CString}, os::raw::{c_char, c_int, c_uchar, c_void}, ptr};
+use std::{ffi::{CStr, CString}, os::raw::{c_char, c_int, c_uchar, c_uint, c_void}, ptr};
+
+use std::sync::atomic::{AtomicBool, Ordering};
 
 use crate::x11;
 
@@ -7,16 +9,30 @@ pub const DRAWABLE_TYPE: c_int = 0x8010;
 pub const RENDER_TYPE: c_int = 0x8011;
 pub const RGBA_BIT: c_int = 0x00000001;
 pub const WINDOW_BIT: c_int = 0x00000001;
+pub const PBUFFER_BIT: c_int = 0x00000004;
+pub const PBUFFER_WIDTH: c_int = 0x8041;
+pub const PBUFFER_HEIGHT: c_int = 0x8040;
 pub const RED_SIZE: c_int = 8;
 pub const GREEN_SIZE: c_int = 9;
 pub const BLUE_SIZE: c_int = 10;
 pub const ALPHA_SIZE: c_int = 11;
 pub const DEPTH_SIZE: c_int = 12;
 pub const DOUBLEBUFFER: c_int = 5;
+pub const STENCIL_SIZE: c_int = 13;
+pub const CONFIG_CAVEAT: c_int = 0x20;
+pub const SLOW_CONFIG: c_int = 0x8001;
+pub const VISUAL_ID: c_int = 0x800B;
+pub const FRAMEBUFFER_SRGB_CAPABLE_ARB: c_int = 0x20B2;
+pub const SAMPLE_BUFFERS: c_int = 100000;
+pub const SAMPLES: c_int = 100001;
 pub const CONTEXT_MAJOR_VERSION_ARB: c_int = 0x2091;
 pub const CONTEXT_MINOR_VERSION_ARB: c_int = 0x2092;
 pub const CONTEXT_PROFILE_MASK_ARB: c_int = 0x9126;
 pub const CONTEXT_CORE_PROFILE_BIT_ARB: c_int = 0x00000001;
+pub const CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB: c_int = 0x00000002;
+pub const CONTEXT_FLAGS_ARB: c_int = 0x2094;
+pub const CONTEXT_DEBUG_BIT_ARB: c_int = 0x0001;
+pub const RGBA_TYPE: c_int = 0x8014;
 
 const DIRECT_RENDERING: i32 = 1;
 
@@ -28,6 +44,13 @@ unsafe extern "C" {
         attrib_list: *const c_int,
         nelements: *mut c_int,
     ) -> *mut *mut c_void;
+    pub unsafe fn glXGetFBConfigs(dpy: *mut c_void, screen: c_int, nelements: *mut c_int) -> *mut *mut c_void;
+    pub unsafe fn glXGetFBConfigAttrib(
+        dpy: *mut c_void,
+        config: *mut c_void,
+        attribute: c_int,
+        value: *mut c_int,
+    ) -> c_int;
     pub unsafe fn glXGetVisualFromFBConfig(dpy: *mut c_void, config: *mut c_void) -> *mut x11::XVisualInfo;
     pub unsafe fn glXGetProcAddress(procName: *const c_uchar) -> *mut c_void;
     pub unsafe fn glXCreateContext(
@@ -36,71 +59,515 @@ unsafe extern "C" {
         share_list: *mut c_void,
         direct: c_int,
     ) -> *mut c_void;
+    pub unsafe fn glXCreateNewContext(
+        dpy: *mut c_void,
+        config: *mut c_void,
+        render_type: c_int,
+        share_list: *mut c_void,
+        direct: c_int,
+    ) -> *mut c_void;
     pub unsafe fn glXMakeCurrent(dpy: *mut c_void, drawable: x11::Window, ctx: *mut c_void) -> c_int;
+    pub unsafe fn glXMakeContextCurrent(
+        dpy: *mut c_void,
+        draw: x11::Window,
+        read: x11::Window,
+        ctx: *mut c_void,
+    ) -> c_int;
+    pub unsafe fn glXCreatePbuffer(dpy: *mut c_void, config: *mut c_void, attrib_list: *const c_int) -> x11::Window;
+    pub unsafe fn glXDestroyPbuffer(dpy: *mut c_void, pbuf: x11::Window);
     pub unsafe fn glXSwapBuffers(dpy: *mut c_void, drawable: x11::Window);
     pub unsafe fn glXDestroyContext(dpy: *mut c_void, ctx: *mut c_void);
+    pub unsafe fn glXQueryExtensionsString(dpy: *mut c_void, screen: c_int) -> *const c_char;
 }
 
-pub fn create_gl_context(dpy: *mut c_void, window: x11::Window) -> *mut c_void {
+/// The GLX extension string for the default screen.
+///
+/// # Safety
+///
+/// `dpy` must be an open display.
+pub unsafe fn extensions(dpy: *mut c_void) -> String {
     unsafe {
-        let screen_id = x11::XDefaultScreen(dpy);
-
-        let fb_attrs = [
-            X_RENDERABLE, 1,
-            DRAWABLE_TYPE, WINDOW_BIT,
-            RENDER_TYPE, RGBA_BIT,
-            RED_SIZE, 8,
-            GREEN_SIZE, 8,
-            BLUE_SIZE, 8,
-            ALPHA_SIZE, 8,
-            DEPTH_SIZE, 24,
-            DOUBLEBUFFER, 1,
-            0,
-        ];
+        let ptr = glXQueryExtensionsString(dpy, x11::XDefaultScreen(dpy));
+        if ptr.is_null() {
+            return String::new();
+        }
+        CStr::from_ptr(ptr).to_string_lossy().into_owned()
+    }
+}
 
-        let mut nelements: c_int = 0;
-        let fbconfigs = glXChooseFBConfig(dpy, screen_id, fb_attrs.as_ptr(), &mut nelements);
-        assert!(!fbconfigs.is_null() && nelements > 0, "No FBConfig found");
+/// # Safety
+///
+/// `dpy` must be an open display.
+pub unsafe fn has_extension(dpy: *mut c_void, name: &str) -> bool {
+    unsafe { extensions(dpy) }.split_whitespace().any(|ext| ext == name)
+}
 
-        let fbconfig = *fbconfigs.add(0);
+fn get_proc(name: &CStr) -> *mut c_void {
+    unsafe { glXGetProcAddress(name.as_ptr() as *const c_uchar) }
+}
 
-        let proc_name = b"glXCreateContextAttribsARB\0";
-        let ptr = glXGetProcAddress(proc_name.as_ptr());
-        if ptr.is_null() {
-            panic!("glXCreateContextAttribsARB not supported");
-        }
-
-        let create_context_arb: extern "C" fn(
-            *mut c_void,
-            *mut c_void,
-            *mut c_void,
-            c_int,
-            *const c_int,
-        ) -> *mut c_void = std::mem::transmute(ptr);
-
-        let ctx_attribs = [
-            CONTEXT_MAJOR_VERSION_ARB, 4,
-            CONTEXT_MINOR_VERSION_ARB, 2,
-            CONTEXT_PROFILE_MASK_ARB, CONTEXT_CORE_PROFILE_BIT_ARB,
-            0,
-        ];
-
-        let ctx = create_context_arb(
-            dpy,
-            fbconfig,
-            ptr::null_mut(),
-            DIRECT_RENDERING,
-            ctx_attribs.as_ptr(),
-        );
-        assert!(!ctx.is_null(), "Failed to create OpenGL 4.2 core context");
-
-        let make_current_ret = glXMakeCurrent(dpy, window, ctx);
-        assert!(make_current_ret != 0, "Failed to make GL context current");
+/// Sets the swap interval for `drawable`: 0 disables vsync, 1 syncs every
+/// vblank and -1 requests adaptive vsync (tear instead of stalling when a
+/// frame runs late). Tries the EXT, MESA and SGI extensions in that order
+/// and returns the name of the one used.
+///
+/// # Safety
+///
+/// `dpy` must be an open display and `drawable` one of its windows, with
+/// a context current on it.
+pub unsafe fn set_swap_interval(dpy: *mut c_void, drawable: x11::Window, interval: c_int) -> Result<&'static str, String> {
+    let has = |name: &str| unsafe { has_extension(dpy, name) };
+
+    if interval < 0 && !has("GLX_EXT_swap_control_tear") {
+        return Err("adaptive vsync needs GLX_EXT_swap_control_tear".into());
+    }
+
+    unsafe {
+        if has("GLX_EXT_swap_control") {
+            let ptr = get_proc(c"glXSwapIntervalEXT");
+            if !ptr.is_null() {
+                let swap_interval: extern "C" fn(*mut c_void, x11::Window, c_int) = std::mem::transmute(ptr);
+                swap_interval(dpy, drawable, interval);
+                return Ok("GLX_EXT_swap_control");
+            }
+        }
+
+        if interval >= 0 && has("GLX_MESA_swap_control") {
+            let ptr = get_proc(c"glXSwapIntervalMESA");
+            if !ptr.is_null() {
+                let swap_interval: extern "C" fn(c_uint) -> c_int = std::mem::transmute(ptr);
+                if swap_interval(interval as c_uint) == 0 {
+                    return Ok("GLX_MESA_swap_control");
+                }
+            }
+        }
+
+        // SGI can't turn syncing off, only choose how many vblanks to wait.
+        if interval > 0 && has("GLX_SGI_swap_control") {
+            let ptr = get_proc(c"glXSwapIntervalSGI");
+            if !ptr.is_null() {
+                let swap_interval: extern "C" fn(c_int) -> c_int = std::mem::transmute(ptr);
+                if swap_interval(interval) == 0 {
+                    return Ok("GLX_SGI_swap_control");
+                }
+            }
+        }
+    }
+
+    Err(format!("no GLX swap control extension supports interval {interval}"))
+}
+
+/// What the window's framebuffer should look like. Every config the server
+/// offers is scored against this rather than asking `glXChooseFBConfig` for
+/// an exact match, so an unsupported request (say 16x MSAA) degrades to the
+/// closest config instead of failing.
+#[derive(Debug, Clone, Copy)]
+pub struct FbConfigRequest {
+    pub red_bits: c_int,
+    pub green_bits: c_int,
+    pub blue_bits: c_int,
+    pub alpha_bits: c_int,
+    pub depth_bits: c_int,
+    pub stencil_bits: c_int,
+    pub srgb: bool,
+    pub samples: c_int,
+    pub double_buffer: bool,
+    /// Match configs that can back a pbuffer instead of a window.
+    pub pbuffer: bool,
+}
+
+impl Default for FbConfigRequest {
+    fn default() -> Self {
+        Self {
+            red_bits: 8,
+            green_bits: 8,
+            blue_bits: 8,
+            alpha_bits: 8,
+            depth_bits: 24,
+            stencil_bits: 8,
+            srgb: false,
+            samples: 0,
+            double_buffer: true,
+            pbuffer: false,
+        }
+    }
+}
+
+/// A chosen FBConfig and the attributes it actually has. The handle is owned
+/// by the display and stays valid for its lifetime.
+#[derive(Debug, Clone, Copy)]
+pub struct FbConfig {
+    pub raw: *mut c_void,
+    pub red_bits: c_int,
+    pub green_bits: c_int,
+    pub blue_bits: c_int,
+    pub alpha_bits: c_int,
+    pub depth_bits: c_int,
+    pub stencil_bits: c_int,
+    pub srgb: bool,
+    pub samples: c_int,
+    pub double_buffer: bool,
+    caveat: c_int,
+}
+
+impl FbConfig {
+    fn query(dpy: *mut c_void, raw: *mut c_void, pbuffer: bool) -> Option<Self> {
+        let attrib = |attribute: c_int| {
+            let mut value = 0;
+            unsafe { glXGetFBConfigAttrib(dpy, raw, attribute, &mut value) };
+            value
+        };
+
+        let usable = if pbuffer {
+            attrib(DRAWABLE_TYPE) & PBUFFER_BIT != 0
+        } else {
+            attrib(X_RENDERABLE) != 0 && attrib(DRAWABLE_TYPE) & WINDOW_BIT != 0 && attrib(VISUAL_ID) != 0
+        } && attrib(RENDER_TYPE) & RGBA_BIT != 0;
+        if !usable {
+            return None;
+        }
+
+        Some(Self {
+            raw,
+            red_bits: attrib(RED_SIZE),
+            green_bits: attrib(GREEN_SIZE),
+            blue_bits: attrib(BLUE_SIZE),
+            alpha_bits: attrib(ALPHA_SIZE),
+            depth_bits: attrib(DEPTH_SIZE),
+            stencil_bits: attrib(STENCIL_SIZE),
+            srgb: attrib(FRAMEBUFFER_SRGB_CAPABLE_ARB) != 0,
+            samples: if attrib(SAMPLE_BUFFERS) != 0 { attrib(SAMPLES) } else { 0 },
+            double_buffer: attrib(DOUBLEBUFFER) != 0,
+            caveat: attrib(CONFIG_CAVEAT),
+        })
+    }
+
+    /// Lower is better. Missing what was asked for costs far more than
+    /// having extra, and a software-fallback config is a last resort.
+    fn score(&self, req: &FbConfigRequest) -> i64 {
+        fn bits(have: c_int, want: c_int) -> i64 {
+            let diff = (have - want) as i64;
+            if diff < 0 { -diff * 1000 } else { diff }
+        }
+
+        let mut score = 0;
+        if self.double_buffer != req.double_buffer {
+            score += 1_000_000;
+        }
+        if self.caveat == SLOW_CONFIG {
+            score += 500_000;
+        }
+        score += bits(self.red_bits, req.red_bits)
+            + bits(self.green_bits, req.green_bits)
+            + bits(self.blue_bits, req.blue_bits)
+            + bits(self.alpha_bits, req.alpha_bits)
+            + bits(self.depth_bits, req.depth_bits)
+            + bits(self.stencil_bits, req.stencil_bits);
+        if req.srgb && !self.srgb {
+            score += 5_000;
+        }
+        // Fewer samples than asked costs per missing doubling, so 8x
+        // degrades to 4x before it degrades to none.
+        let log2 = |n: c_int| if n > 1 { 32 - (n as u32).leading_zeros() as i64 } else { 0 };
+        let missing = log2(req.samples) - log2(self.samples);
+        score += if missing > 0 { missing * 2_000 } else { -missing * 10 };
+        score
+    }
+}
+
+impl std::fmt::Display for FbConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "RGBA{}{}{}{} D{} S{}, {} samples{}{}",
+            self.red_bits, self.green_bits, self.blue_bits, self.alpha_bits,
+            self.depth_bits, self.stencil_bits, self.samples,
+            if self.srgb { ", sRGB" } else { "" },
+            if self.double_buffer { ", double-buffered" } else { "" },
+        )
+    }
+}
+
+/// Enumerates every FBConfig on `screen` and returns the one closest to
+/// `req`. Window and context creation must both use the result so the
+/// context matches the window's visual.
+///
+/// # Safety
+///
+/// `dpy` must be an open display.
+pub unsafe fn choose_fb_config(dpy: *mut c_void, screen: c_int, req: &FbConfigRequest) -> Option<FbConfig> {
+    unsafe {
+        let mut nelements: c_int = 0;
+        let fbconfigs = glXGetFBConfigs(dpy, screen, &mut nelements);
+        if fbconfigs.is_null() {
+            return None;
+        }
+
+        let best = (0..nelements as usize)
+            .filter_map(|i| FbConfig::query(dpy, *fbconfigs.add(i), req.pbuffer))
+            .min_by_key(|config| config.score(req));
 
         x11::XFree(fbconfigs as *mut c_void);
+        best
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct GlVersion {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl GlVersion {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// Parses the leading "major.minor" of a `GL_VERSION` string.
+    fn parse(version: &str) -> Option<Self> {
+        let mut parts = version.split(|c: char| !c.is_ascii_digit());
+        let major = parts.next()?.parse().ok()?;
+        let minor = parts.next()?.parse().ok()?;
+        Some(Self { major, minor })
+    }
+}
 
-        println!("OpenGL 4.2 context current");
-        ctx
+impl std::fmt::Display for GlVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Profile {
+    Core,
+    Compatibility,
+}
+
+impl std::fmt::Display for Profile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Profile::Core => write!(f, "core"),
+            Profile::Compatibility => write!(f, "compatibility"),
+        }
+    }
+}
+
+/// The oldest OpenGL the shaders run on, as they are `#version 420`.
+pub const MIN_VERSION: GlVersion = GlVersion::new(4, 2);
+
+/// Every core version from 4.6 down to `MIN_VERSION`, then the same again
+/// as compatibility profiles for drivers that only expose those.
+pub const DEFAULT_VERSIONS: &[(GlVersion, Profile)] = &[
+    (GlVersion::new(4, 6), Profile::Core),
+    (GlVersion::new(4, 5), Profile::Core),
+    (GlVersion::new(4, 4), Profile::Core),
+    (GlVersion::new(4, 3), Profile::Core),
+    (GlVersion::new(4, 2), Profile::Core),
+    (GlVersion::new(4, 6), Profile::Compatibility),
+    (GlVersion::new(4, 5), Profile::Compatibility),
+    (GlVersion::new(4, 4), Profile::Compatibility),
+    (GlVersion::new(4, 3), Profile::Compatibility),
+    (GlVersion::new(4, 2), Profile::Compatibility),
+];
+
+/// A current GL context and what the driver actually gave us, which can be
+/// newer than what was requested.
+pub struct GlContext {
+    pub raw: *mut c_void,
+    pub version: GlVersion,
+    pub profile: Profile,
+}
+
+impl GlContext {
+    pub fn supports(&self, version: GlVersion) -> bool {
+        self.version >= version
+    }
+}
+
+pub struct ContextBuilder {
+    versions: Vec<(GlVersion, Profile)>,
+    debug: bool,
+}
+
+impl Default for ContextBuilder {
+    fn default() -> Self {
+        Self {
+            versions: DEFAULT_VERSIONS.to_vec(),
+            debug: false,
+        }
+    }
+}
+
+type CreateContextAttribsFn = extern "C" fn(*mut c_void, *mut c_void, *mut c_void, c_int, *const c_int) -> *mut c_void;
+
+static CONTEXT_ERROR: AtomicBool = AtomicBool::new(false);
+
+/// Swallows the BadMatch/GLXBadFBConfig a refused context version raises,
+/// which would otherwise kill the process in Xlib's default handler.
+extern "C" fn record_context_error(_dpy: *mut c_void, _ev: *mut c_void) -> c_int {
+    CONTEXT_ERROR.store(true, Ordering::Relaxed);
+    0
+}
+
+impl ContextBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Versions and profiles to try, most preferred first.
+    pub fn versions(mut self, versions: &[(GlVersion, Profile)]) -> Self {
+        self.versions = versions.to_vec();
+        self
+    }
+
+    /// Requests a debug context (`GLX_CONTEXT_DEBUG_BIT_ARB`), which makes
+    /// drivers report far more through `KHR_debug`.
+    pub fn debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Creates a context on `fbconfig` and makes it current on `drawable`
+    /// (a window or pbuffer), trying each requested version in turn.
+    /// Without `GLX_ARB_create_context` a legacy context is the only option.
+    ///
+    /// # Safety
+    ///
+    /// `dpy` must be an open display, `drawable` one of its windows or
+    /// pbuffers and `fbconfig` the config `drawable` was created with.
+    pub unsafe fn build(&self, dpy: *mut c_void, drawable: x11::Window, fbconfig: &FbConfig) -> Result<GlContext, String> {
+        unsafe {
+            let ptr = glXGetProcAddress(c"glXCreateContextAttribsARB".as_ptr() as *const c_uchar);
+            let ctx = if ptr.is_null() || !has_extension(dpy, "GLX_ARB_create_context") {
+                println!("glXCreateContextAttribsARB not supported, creating a legacy context");
+                glXCreateNewContext(dpy, fbconfig.raw, RGBA_TYPE, ptr::null_mut(), DIRECT_RENDERING)
+            } else {
+                let create_context_arb: CreateContextAttribsFn = std::mem::transmute(ptr);
+                self.create_versioned(dpy, fbconfig, create_context_arb)
+                    .ok_or_else(|| format!("none of the requested OpenGL versions are available, and the shaders need {MIN_VERSION}"))?
+            };
+            if ctx.is_null() {
+                return Err("failed to create an OpenGL context".into());
+            }
+
+            if glXMakeContextCurrent(dpy, drawable, drawable, ctx) == 0 {
+                glXDestroyContext(dpy, ctx);
+                return Err("failed to make GL context current".into());
+            }
+
+            let Some(version) = current_version() else {
+                glXMakeContextCurrent(dpy, 0, 0, ptr::null_mut());
+                glXDestroyContext(dpy, ctx);
+                return Err("GL context reports no version".into());
+            };
+            let context = GlContext { raw: ctx, version, profile: current_profile(version, get_proc) };
+            println!("OpenGL {} {} context current", context.version, context.profile);
+            Ok(context)
+        }
+    }
+
+    unsafe fn create_versioned(
+        &self,
+        dpy: *mut c_void,
+        fbconfig: &FbConfig,
+        create_context_arb: CreateContextAttribsFn,
+    ) -> Option<*mut c_void> {
+        unsafe {
+            x11::XSync(dpy, 0);
+            let old_handler = x11::XSetErrorHandler(Some(record_context_error));
+
+            let mut result = None;
+            for &(version, profile) in &self.versions {
+                let profile_bit = match profile {
+                    Profile::Core => CONTEXT_CORE_PROFILE_BIT_ARB,
+                    Profile::Compatibility => CONTEXT_COMPATIBILITY_PROFILE_BIT_ARB,
+                };
+                let ctx_attribs = [
+                    CONTEXT_MAJOR_VERSION_ARB, version.major as c_int,
+                    CONTEXT_MINOR_VERSION_ARB, version.minor as c_int,
+                    CONTEXT_PROFILE_MASK_ARB, profile_bit,
+                    CONTEXT_FLAGS_ARB, if self.debug { CONTEXT_DEBUG_BIT_ARB } else { 0 },
+                    0,
+                ];
+
+                CONTEXT_ERROR.store(false, Ordering::Relaxed);
+                let ctx = create_context_arb(
+                    dpy,
+                    fbconfig.raw,
+                    ptr::null_mut(),
+                    DIRECT_RENDERING,
+                    ctx_attribs.as_ptr(),
+                );
+                x11::XSync(dpy, 0);
+
+                if !ctx.is_null() && !CONTEXT_ERROR.load(Ordering::Relaxed) {
+                    result = Some(ctx);
+                    break;
+                }
+                println!("OpenGL {version} {profile} context unavailable");
+            }
+
+            x11::XSetErrorHandler(old_handler);
+            result
+        }
+    }
+}
+
+/// An invisible drawable to make a context current on, for rendering
+/// without a window. Destroy with `glXDestroyPbuffer`.
+///
+/// # Safety
+///
+/// `dpy` must be an open display and `fbconfig` one of its configs.
+pub unsafe fn create_pbuffer(dpy: *mut c_void, fbconfig: &FbConfig, width: u16, height: u16) -> Result<x11::Window, String> {
+    let attribs = [PBUFFER_WIDTH, width as c_int, PBUFFER_HEIGHT, height as c_int, 0];
+    let pbuffer = unsafe { glXCreatePbuffer(dpy, fbconfig.raw, attribs.as_ptr()) };
+    if pbuffer == 0 {
+        return Err("glXCreatePbuffer failed".into());
+    }
+    Ok(pbuffer)
+}
+
+/// Reads `GL_VERSION` from the current context. Works before the `gl`
+/// function pointers are loaded.
+fn current_version() -> Option<GlVersion> {
+    unsafe {
+        let ptr = get_proc(c"glGetString");
+        if ptr.is_null() {
+            return None;
+        }
+        let get_string: extern "C" fn(c_uint) -> *const c_char = std::mem::transmute(ptr);
+        let version = get_string(gl::VERSION);
+        if version.is_null() {
+            return None;
+        }
+        GlVersion::parse(&CStr::from_ptr(version).to_string_lossy())
+    }
+}
+
+/// Reads `GL_CONTEXT_PROFILE_MASK` from the current context, which can
+/// differ from the profile asked for: legacy contexts and drivers that
+/// only do compatibility hand out what they have. Contexts older than 3.2
+/// have no profiles and count as compatibility. `get_proc` is the window
+/// system's loader, so this works before the `gl` function pointers are.
+pub fn current_profile(version: GlVersion, get_proc: impl Fn(&CStr) -> *mut c_void) -> Profile {
+    if version < GlVersion::new(3, 2) {
+        return Profile::Compatibility;
+    }
+    unsafe {
+        let ptr = get_proc(c"glGetIntegerv");
+        if ptr.is_null() {
+            return Profile::Compatibility;
+        }
+        let get_integerv: extern "C" fn(c_uint, *mut c_int) = std::mem::transmute(ptr);
+        let mut mask: c_int = 0;
+        get_integerv(gl::CONTEXT_PROFILE_MASK, &mut mask);
+        if mask & gl::CONTEXT_CORE_PROFILE_BIT as c_int != 0 {
+            Profile::Core
+        } else {
+            Profile::Compatibility
+        }
     }
 }
 
@@ -110,7 +577,12 @@ pub fn init_gl_functions() {
             let c_name = CString::new(name).unwrap();
             glXGetProcAddress(c_name.as_ptr() as *const c_uchar) as *const _
         });
+    }
+    report_gl_info();
+}
 
+pub fn report_gl_info() {
+    unsafe {
         let ver_ptr = gl::GetString(gl::VERSION);
         if !ver_ptr.is_null() {
             println!("OpenGL version: {:?}", CStr::from_ptr(ver_ptr as *const c_char));
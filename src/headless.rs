@@ -0,0 +1,210 @@
+use std::os::raw::c_void;
+
+use crate::cli::Options;
+use crate::framebuffer::{Framebuffer, FramebufferDesc, Storage};
+use crate::glx::{self, GlContext};
+use crate::render::{DemoScene, Renderer, SceneState};
+use crate::recording::Recorder;
+use crate::scenefile::SceneFile;
+use crate::{egl, gldebug, msaa, screenshot, x11};
+
+pub const DEFAULT_SIZE: (u16, u16) = (1280, 720);
+
+/// Simulated time per headless frame, so output doesn't depend on how
+/// fast the machine renders.
+pub const FRAME_DELTA: f32 = 1.0 / 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backend {
+    /// EGL surfaceless if available, otherwise a GLX pbuffer.
+    Auto,
+    Egl,
+    Pbuffer,
+}
+
+/// A GL context that isn't attached to anything visible.
+pub enum HeadlessContext {
+    Egl(egl::SurfacelessContext),
+    Pbuffer {
+        dpy: *mut c_void,
+        pbuffer: x11::Window,
+        context: GlContext,
+    },
+}
+
+impl HeadlessContext {
+    pub fn new(backend: Backend, opts: &Options) -> Result<Self, String> {
+        match backend {
+            Backend::Egl => egl::SurfacelessContext::new(&opts.gl_versions(), opts.gl_debug).map(HeadlessContext::Egl),
+            Backend::Pbuffer => Self::pbuffer(opts),
+            Backend::Auto => Self::new(Backend::Egl, opts).or_else(|e| {
+                println!("EGL surfaceless unavailable ({e}), trying a GLX pbuffer");
+                Self::pbuffer(opts)
+            }),
+        }
+    }
+
+    fn pbuffer(opts: &Options) -> Result<Self, String> {
+        unsafe {
+            let dpy = x11::XOpenDisplay(std::ptr::null());
+            if dpy.is_null() {
+                return Err("cannot open X display for a GLX pbuffer".into());
+            }
+            match Self::pbuffer_context(dpy, opts) {
+                Ok((pbuffer, context)) => Ok(HeadlessContext::Pbuffer { dpy, pbuffer, context }),
+                Err(e) => {
+                    x11::XCloseDisplay(dpy);
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    /// A pbuffer on `dpy` with a current context on it, or neither.
+    unsafe fn pbuffer_context(dpy: *mut c_void, opts: &Options) -> Result<(x11::Window, GlContext), String> {
+        unsafe {
+            let fb_request = glx::FbConfigRequest {
+                double_buffer: false,
+                pbuffer: true,
+                ..Default::default()
+            };
+            let screen = x11::XDefaultScreen(dpy);
+            let fbconfig = glx::choose_fb_config(dpy, screen, &fb_request).ok_or("no pbuffer-capable FBConfig")?;
+            // Rendering goes to an FBO, the pbuffer only gives the context
+            // something to be current on.
+            let pbuffer = glx::create_pbuffer(dpy, &fbconfig, 1, 1)?;
+            let context = glx::ContextBuilder::new()
+                .versions(&opts.gl_versions())
+                .debug(opts.gl_debug)
+                .build(dpy, pbuffer, &fbconfig);
+            match context {
+                Ok(context) => {
+                    glx::init_gl_functions();
+                    Ok((pbuffer, context))
+                }
+                Err(e) => {
+                    glx::glXDestroyPbuffer(dpy, pbuffer);
+                    Err(e)
+                }
+            }
+        }
+    }
+
+    pub fn context(&self) -> &GlContext {
+        match self {
+            HeadlessContext::Egl(egl) => &egl.context,
+            HeadlessContext::Pbuffer { context, .. } => context,
+        }
+    }
+}
+
+impl Drop for HeadlessContext {
+    fn drop(&mut self) {
+        if let HeadlessContext::Pbuffer { dpy, pbuffer, context } = self {
+            unsafe {
+                glx::glXMakeContextCurrent(*dpy, 0, 0, std::ptr::null_mut());
+                glx::glXDestroyContext(*dpy, context.raw);
+                glx::glXDestroyPbuffer(*dpy, *pbuffer);
+                x11::XCloseDisplay(*dpy);
+            }
+        }
+    }
+}
+
+/// Renders `opts.frames` frames of the scene without a window, then writes
+/// the last one to `opts.output` if given. With `--record` every frame is
+/// recorded too, stepping the scene at the recording's frame rate.
+pub fn run(opts: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let (width, height) = opts.size.unwrap_or(DEFAULT_SIZE);
+    let headless = HeadlessContext::new(opts.headless_backend, opts)?;
+    let ctx = headless.context();
+
+    if opts.gl_debug
+        && let Err(e) = gldebug::install(&opts.gl_debug_filter)
+    {
+        println!("GL debug output disabled: {e}");
+    }
+    if !ctx.supports(glx::MIN_VERSION) {
+        return Err(format!("OpenGL {} is too old, the shaders need {}", ctx.version, glx::MIN_VERSION).into());
+    }
+
+    unsafe {
+        gl::Enable(gl::DEPTH_TEST);
+        gl::Viewport(0, 0, width as i32, height as i32);
+    }
+
+    opts.culling.apply();
+
+    // A surfaceless context has no default framebuffer, so this stands in.
+    let desc = FramebufferDesc {
+        colour: Some(Storage::Renderbuffer),
+        depth: Some(Storage::Renderbuffer),
+        samples: 0,
+    };
+    let target = Framebuffer::new(width, height, desc, "Headless framebuffer")?;
+    let msaa_target = if opts.msaa_samples > 1 {
+        msaa::MultisampleTarget::new(width, height, opts.msaa_samples)
+            .map_err(|e| println!("MSAA disabled: {e}"))
+            .ok()
+    } else {
+        None
+    };
+
+    let scene_file = match &opts.scene {
+        Some(path) => SceneFile::load(path)?,
+        None => SceneFile::default(),
+    };
+    let cam = scene_file.camera.to_camera();
+    let mut renderer = Renderer::new(opts.shadow);
+    let mut demo = DemoScene::new(scene_file);
+    let mut state = SceneState {
+        colourmode: opts.colourmode,
+        only: opts.shape,
+        show_back_faces: opts.show_back_faces,
+        lighting: !demo.scene.lights.is_empty(),
+        shading: opts.shading,
+        attenuation: opts.attenuation,
+        shadows: opts.shadows,
+        shadow_map_view: opts.show_shadow_map.then_some(0),
+        ..SceneState::default()
+    };
+
+    let mut recorder = if opts.record.start {
+        Some(Recorder::start(&opts.record, opts.screenshot.format, opts.screenshot.alpha)?)
+    } else {
+        None
+    };
+    let delta = recorder.as_ref().map_or(FRAME_DELTA, Recorder::timestep);
+
+    let start = std::time::Instant::now();
+    for _ in 0..opts.frames {
+        state.update(delta);
+        demo.update(&state);
+        match &msaa_target {
+            Some(msaa) => msaa.bind(),
+            None => target.bind(),
+        }
+        renderer.draw(&demo.scene, &state, &cam, width, height);
+        if let Some(msaa) = &msaa_target {
+            msaa.resolve(target.id());
+        }
+        if let Some(rec) = &mut recorder {
+            rec.capture(target.id(), width, height);
+        }
+    }
+    unsafe {
+        gl::Finish();
+    }
+    println!("Rendered {} headless frames in {:.2?}", opts.frames, start.elapsed());
+    if let Some(rec) = recorder {
+        rec.finish();
+    }
+
+    if let Some(path) = &opts.output {
+        let alpha = opts.screenshot.alpha;
+        let image = screenshot::capture(target.id(), width, height, alpha);
+        let path = screenshot::write(&image, path, screenshot::Format::from_path(path), alpha)?;
+        println!("Wrote {}", path.display());
+    }
+    Ok(())
+}
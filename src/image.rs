@@ -0,0 +1,84 @@
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// An RGBA8 image stored top row first, the way image files expect it.
+pub struct Image {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl Image {
+    /// Reads the bound read framebuffer. GL returns rows bottom-up, so they
+    /// are flipped here.
+    pub fn read_framebuffer(width: u32, height: u32) -> Self {
+        let stride = width as usize * 4;
+        let mut pixels = vec![0u8; stride * height as usize];
+        unsafe {
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            gl::ReadPixels(
+                0,
+                0,
+                width as i32,
+                height as i32,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                pixels.as_mut_ptr() as *mut _,
+            );
+        }
+        let mut image = Self { width, height, pixels };
+        image.flip_vertically();
+        image
+    }
+
+    pub fn flip_vertically(&mut self) {
+        let stride = self.width as usize * 4;
+        let height = self.height as usize;
+        for row in 0..height / 2 {
+            let (top, bottom) = self.pixels.split_at_mut((height - 1 - row) * stride);
+            top[row * stride..(row + 1) * stride].swap_with_slice(&mut bottom[..stride]);
+        }
+    }
+
+    /// Marks every pixel fully opaque. The default framebuffer's alpha is
+    /// whatever blending left behind and means nothing on screen.
+    pub fn make_opaque(&mut self) {
+        for pixel in self.pixels.chunks_exact_mut(4) {
+            pixel[3] = 255;
+        }
+    }
+
+    /// PNG, RGBA if `alpha` is set and RGB otherwise. The pixels are what
+    /// was sent to the display, i.e. sRGB-encoded whether or not
+    /// `GL_FRAMEBUFFER_SRGB` did the encoding, so the file is tagged sRGB.
+    pub fn write_png(&self, path: &Path, alpha: bool) -> Result<(), png::EncodingError> {
+        let out = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(out, self.width, self.height);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+
+        let mut writer;
+        if alpha {
+            encoder.set_color(png::ColorType::Rgba);
+            writer = encoder.write_header()?;
+            writer.write_image_data(&self.pixels)?;
+        } else {
+            encoder.set_color(png::ColorType::Rgb);
+            writer = encoder.write_header()?;
+            let rgb: Vec<u8> = self.pixels.chunks_exact(4).flat_map(|p| [p[0], p[1], p[2]]).collect();
+            writer.write_image_data(&rgb)?;
+        }
+        writer.finish()
+    }
+
+    /// Binary PPM (P6). PPM has no alpha channel, so it is dropped.
+    pub fn write_ppm(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        write!(out, "P6\n{} {}\n255\n", self.width, self.height)?;
+        for pixel in self.pixels.chunks_exact(4) {
+            out.write_all(&pixel[..3])?;
+        }
+        out.flush()
+    }
+}
@@ -0,0 +1,321 @@
+//! Scene lights. Every frame the renderer packs the enabled ones into a
+//! std140 uniform block at binding `LIGHTS_BINDING`, which the lit
+//! shaders declare through `shaders/lights.glsl` and loop over.
+
+use gl::types::GLuint;
+use glam::{Mat4, Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::shadow::{MAX_POINT_SHADOWS, MAX_SHADOWS};
+
+/// Must match `MAX_LIGHTS` in `shaders/lights.glsl`.
+pub const MAX_LIGHTS: usize = 16;
+
+/// Must match the `binding` of the `Lights` block in `shaders/lights.glsl`.
+pub const LIGHTS_BINDING: GLuint = 0;
+
+/// One `Light` in the block: four vec4s.
+const LIGHT_STRIDE: usize = 64;
+
+/// The light count, padded to a vec4, then the array.
+pub const BLOCK_SIZE: usize = 16 + MAX_LIGHTS * LIGHT_STRIDE;
+
+/// The numbering is what the shader switches on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LightKind {
+    /// Infinitely far away, like the sun: only `direction` matters.
+    Directional = 0,
+    /// Shines every way from `position`.
+    Point = 1,
+    /// Shines from `position` along `direction` in a cone.
+    Spot = 2,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Light {
+    pub kind: LightKind,
+    /// World space. Unused by directional lights.
+    #[serde(default)]
+    pub position: Vec3,
+    /// The way the light travels. Unused by point lights.
+    #[serde(default = "down")]
+    pub direction: Vec3,
+    #[serde(default = "white")]
+    pub colour: Vec3,
+    #[serde(default = "unit")]
+    pub intensity: f32,
+    /// Distance at which a point or spot light has faded out completely.
+    /// Zero means it never does.
+    #[serde(default)]
+    pub range: f32,
+    /// Spot cone half-angles in degrees: full brightness inside
+    /// `inner_cone`, fading out to nothing at `outer_cone`.
+    #[serde(default = "default_inner_cone")]
+    pub inner_cone: f32,
+    #[serde(default = "default_outer_cone")]
+    pub outer_cone: f32,
+    #[serde(default = "yes")]
+    pub enabled: bool,
+    /// Whether the light is blocked by the scene.
+    #[serde(default = "yes")]
+    pub cast_shadows: bool,
+}
+
+fn down() -> Vec3 {
+    Vec3::NEG_Y
+}
+
+fn white() -> Vec3 {
+    Vec3::ONE
+}
+
+fn unit() -> f32 {
+    1.0
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn default_inner_cone() -> f32 {
+    20.0
+}
+
+fn default_outer_cone() -> f32 {
+    30.0
+}
+
+impl Light {
+    fn new(kind: LightKind) -> Self {
+        Self {
+            kind,
+            position: Vec3::ZERO,
+            direction: down(),
+            colour: white(),
+            intensity: 1.0,
+            range: 0.0,
+            inner_cone: default_inner_cone(),
+            outer_cone: default_outer_cone(),
+            enabled: true,
+            cast_shadows: true,
+        }
+    }
+
+    pub fn directional(direction: Vec3) -> Self {
+        Self {
+            direction,
+            ..Self::new(LightKind::Directional)
+        }
+    }
+
+    pub fn point(position: Vec3, range: f32) -> Self {
+        Self {
+            position,
+            range,
+            ..Self::new(LightKind::Point)
+        }
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, range: f32) -> Self {
+        Self {
+            position,
+            direction,
+            range,
+            ..Self::new(LightKind::Spot)
+        }
+    }
+
+    /// Catches what the parser can't.
+    pub fn check(&self) -> Result<(), String> {
+        if self.kind != LightKind::Point && self.direction.length_squared() == 0.0 {
+            return Err("light direction is zero".into());
+        }
+        if self.range < 0.0 || self.intensity < 0.0 {
+            return Err("light range and intensity can't be negative".into());
+        }
+        if self.kind == LightKind::Spot && !(0.0 <= self.inner_cone && self.inner_cone <= self.outer_cone && self.outer_cone < 90.0) {
+            return Err("spot cones need 0 <= inner_cone <= outer_cone < 90 degrees".into());
+        }
+        Ok(())
+    }
+}
+
+/// The directional and spot lights that get a 2D shadow map, in layer
+/// order: the first `MAX_SHADOWS` of the packed ones that cast shadows.
+pub fn shadow_casters(lights: &[Light]) -> impl Iterator<Item = &Light> {
+    casters(lights, false).take(MAX_SHADOWS)
+}
+
+/// The point lights that get a cube shadow map, in layer order: the first
+/// `MAX_POINT_SHADOWS` of the packed ones that cast shadows.
+pub fn point_shadow_casters(lights: &[Light]) -> impl Iterator<Item = &Light> {
+    casters(lights, true).take(MAX_POINT_SHADOWS)
+}
+
+fn casters(lights: &[Light], point: bool) -> impl Iterator<Item = &Light> {
+    lights
+        .iter()
+        .filter(|light| light.enabled)
+        .take(MAX_LIGHTS)
+        .filter(move |light| light.cast_shadows && (light.kind == LightKind::Point) == point)
+}
+
+/// Lays out the enabled lights, at most `MAX_LIGHTS` of them, as the
+/// `Lights` block expects, with positions and directions moved into view
+/// space since that's where the shaders light:
+///
+/// ```glsl
+/// struct Light {
+///     vec4 position;  // xyz, w = kind
+///     vec4 direction; // xyz, w = range
+///     vec4 colour;    // rgb premultiplied by intensity
+///     vec4 cone;      // cosines of the inner and outer cone angles,
+///                     // z = shadow map layer or -1
+/// };
+/// ```
+///
+/// With `shadows` the `shadow_casters` get 2D layers 0, 1, 2... in order,
+/// and the `point_shadow_casters` cube layers numbered the same way.
+pub fn pack(lights: &[Light], view: Mat4, shadows: bool) -> Vec<u8> {
+    let mut block = vec![0; BLOCK_SIZE];
+    let enabled = lights.iter().filter(|light| light.enabled).take(MAX_LIGHTS);
+    let mut count: i32 = 0;
+    // 2D layers, then cube layers.
+    let mut layers = [0, 0];
+    for (light, slot) in enabled.zip(block[16..].chunks_exact_mut(LIGHT_STRIDE)) {
+        let point = light.kind == LightKind::Point;
+        let max = if point { MAX_POINT_SHADOWS } else { MAX_SHADOWS };
+        let used = &mut layers[point as usize];
+        let layer = if shadows && light.cast_shadows && *used < max {
+            *used += 1;
+            *used as f32 - 1.0
+        } else {
+            -1.0
+        };
+        let position = view.transform_point3(light.position);
+        let direction = view.transform_vector3(light.direction).normalize_or_zero();
+        let vecs = [
+            position.extend(light.kind as u32 as f32),
+            direction.extend(light.range),
+            (light.colour * light.intensity).extend(1.0),
+            Vec4::new(light.inner_cone.to_radians().cos(), light.outer_cone.to_radians().cos(), layer, 0.0),
+        ];
+        for (float, bytes) in vecs.iter().flat_map(|v| v.to_array()).zip(slot.chunks_exact_mut(4)) {
+            bytes.copy_from_slice(&float.to_ne_bytes());
+        }
+        count += 1;
+    }
+    block[..4].copy_from_slice(&count.to_ne_bytes());
+    block
+}
+
+/// The uniform buffer behind the `Lights` block.
+pub struct LightBuffer {
+    ubo: GLuint,
+}
+
+impl LightBuffer {
+    /// Needs a current context.
+    pub fn new() -> Self {
+        unsafe {
+            let mut ubo = 0;
+            gl::GenBuffers(1, &mut ubo);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, ubo);
+            gl::BufferData(gl::UNIFORM_BUFFER, BLOCK_SIZE as isize, std::ptr::null(), gl::DYNAMIC_DRAW);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+            Self { ubo }
+        }
+    }
+
+    /// Uploads `lights` as seen from `view` and binds the buffer for the
+    /// next draws. `shadows` says whether shadow maps were rendered.
+    pub fn upload(&self, lights: &[Light], view: Mat4, shadows: bool) {
+        let block = pack(lights, view, shadows);
+        unsafe {
+            gl::BindBuffer(gl::UNIFORM_BUFFER, self.ubo);
+            gl::BufferSubData(gl::UNIFORM_BUFFER, 0, block.len() as isize, block.as_ptr() as *const _);
+            gl::BindBuffer(gl::UNIFORM_BUFFER, 0);
+            gl::BindBufferBase(gl::UNIFORM_BUFFER, LIGHTS_BINDING, self.ubo);
+        }
+    }
+}
+
+impl Default for LightBuffer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for LightBuffer {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteBuffers(1, &self.ubo);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn float(block: &[u8], index: usize) -> f32 {
+        f32::from_ne_bytes(block[index * 4..index * 4 + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn pack_skips_disabled_lights_and_moves_them_into_view_space() {
+        let mut off = Light::point(Vec3::ONE, 5.0);
+        off.enabled = false;
+        let spot = Light::spot(Vec3::new(1.0, 2.0, 3.0), Vec3::NEG_Z, 4.0);
+        let view = Mat4::from_translation(Vec3::new(0.0, 0.0, -2.0));
+
+        let block = pack(&[off, spot], view, false);
+        assert_eq!(block.len(), BLOCK_SIZE);
+        assert_eq!(i32::from_ne_bytes(block[..4].try_into().unwrap()), 1);
+        // The first light starts at float 4.
+        let light: Vec<f32> = (4..20).map(|i| float(&block, i)).collect();
+        assert_eq!(light[..4], [1.0, 2.0, 1.0, LightKind::Spot as u32 as f32]);
+        assert_eq!(light[4..8], [0.0, 0.0, -1.0, 4.0]);
+        assert!((light[12] - 20f32.to_radians().cos()).abs() < 1e-6);
+        assert_eq!(light[14], -1.0, "no shadow map");
+    }
+
+    #[test]
+    fn shadow_layers_are_numbered_per_kind_of_map_in_order() {
+        let mut unshadowed = Light::directional(Vec3::NEG_Y);
+        unshadowed.cast_shadows = false;
+        let lights = [
+            Light::point(Vec3::ZERO, 1.0),
+            unshadowed,
+            Light::spot(Vec3::Y, Vec3::NEG_Y, 5.0),
+            Light::point(Vec3::X, 1.0),
+            Light::directional(Vec3::NEG_X),
+        ];
+        let block = pack(&lights, Mat4::IDENTITY, true);
+        // Each light's cone.z.
+        let layers: Vec<f32> = (0..5).map(|i| float(&block, 4 + i * 16 + 14)).collect();
+        assert_eq!(layers, [0.0, -1.0, 0.0, 1.0, 1.0]);
+        let casters: Vec<_> = shadow_casters(&lights).map(|light| light.kind).collect();
+        assert_eq!(casters, [LightKind::Spot, LightKind::Directional]);
+        let points: Vec<_> = point_shadow_casters(&lights).map(|light| light.position).collect();
+        assert_eq!(points, [Vec3::ZERO, Vec3::X]);
+
+        let block = pack(&lights, Mat4::IDENTITY, false);
+        assert!((0..5).all(|i| float(&block, 4 + i * 16 + 14) == -1.0));
+    }
+
+    #[test]
+    fn pack_caps_the_light_count() {
+        let lights = vec![Light::directional(Vec3::NEG_Y); MAX_LIGHTS + 3];
+        let block = pack(&lights, Mat4::IDENTITY, false);
+        assert_eq!(i32::from_ne_bytes(block[..4].try_into().unwrap()), MAX_LIGHTS as i32);
+    }
+
+    #[test]
+    fn bad_spot_cones_are_rejected() {
+        let mut spot = Light::spot(Vec3::ZERO, Vec3::NEG_Y, 0.0);
+        assert!(spot.check().is_ok());
+        spot.inner_cone = 40.0;
+        assert!(spot.check().is_err());
+    }
+}
@@ -1,82 +1,151 @@
+pub mod animation;
+pub mod camera;
+pub mod cli;
+pub mod culling;
+pub mod egl;
+pub mod framebuffer;
+pub mod geometry;
+pub mod gldebug;
 pub mod glx;
+pub mod headless;
+pub mod image;
+pub mod light;
+pub mod material;
+pub mod msaa;
+pub mod pacing;
+pub mod pointer;
 pub mod x11;
+pub mod xinput;
 pub mod window;
 pub mod shape;
+pub mod texture;
 pub mod shader;
+pub mod shading;
+pub mod shadow;
+pub mod skybox;
+pub mod recording;
+pub mod render;
+pub mod scene;
+pub mod scenefile;
+pub mod screenshot;
 
-use std::i16;
+use std::path::Path;
 
-use gl::types::GLuint;
-use glam::{Mat4, Vec3};
+use crate::light::{Light, MAX_LIGHTS};
+use crate::render::{DemoScene, Renderer, SceneState};
+use crate::scenefile::{CameraDesc, SceneFile, SceneWatcher};
 
-use crate::shape::*;
+/// Where F5 saves the scene when it wasn't loaded from a file.
+const DEFAULT_SCENE_PATH: &str = "scene.ron";
 
-const TARGET_FPS: u64 = 60;
-const FRAME_TIME: std::time::Duration = std::time::Duration::from_nanos(1_000_000_000 / TARGET_FPS);
-
-struct Camera {
-    pub x: f32,
-    pub y: f32,
-    pub z: f32,
-    pub yaw: f32,
-    pub pitch: f32,
-}
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    unsafe {
+        let opts = match cli::Options::from_env() {
+            Ok(opts) => opts,
+            Err(cli::CliError::Help) => {
+                println!("{}", cli::CliError::Help);
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                std::process::exit(2);
+            }
+        };
 
-impl Default for Camera {
-    fn default() -> Self {
-        Self {
-            x: 0.0,
-            y: 0.0,
-            z: 2.0,
-            yaw: -std::f32::consts::FRAC_PI_2,
-            pitch: 0.0,
+        if opts.headless {
+            return headless::run(&opts);
         }
-    }
-}
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    unsafe {
+        let scene_file = match &opts.scene {
+            Some(path) => SceneFile::load(path)?,
+            None => SceneFile::default(),
+        };
+
         let dpy = x11::XOpenDisplay(std::ptr::null());
         assert!(!dpy.is_null(), "Cannot open X display");
 
-        const WIDTH: u16 = 1920;
-        const HEIGHT: u16 = 1080;
+        let (mut width, mut height) = opts.size.unwrap_or_else(|| window::default_size(dpy));
+        let mut mode = window::WindowMode {
+            fullscreen: opts.fullscreen,
+            borderless: opts.borderless,
+        };
 
-        let window = window::create(dpy, WIDTH, HEIGHT)?;
-        let _ctx = glx::create_gl_context(dpy, window);
+        let fb_request = glx::FbConfigRequest {
+            samples: if opts.msaa_fbo { 0 } else { opts.msaa_samples as i32 },
+            srgb: opts.srgb,
+            ..Default::default()
+        };
+        let (window, fbconfig) = window::create(dpy, width, height, mode, &fb_request)?;
+        let ctx = glx::ContextBuilder::new()
+            .versions(&opts.gl_versions())
+            .debug(opts.gl_debug)
+            .build(dpy, window, &fbconfig)?;
         glx::init_gl_functions();
+        if opts.gl_debug
+            && let Err(e) = gldebug::install(&opts.gl_debug_filter)
+        {
+            println!("GL debug output disabled: {e}");
+        }
+        if !ctx.supports(glx::MIN_VERSION) {
+            return Err(format!("OpenGL {} is too old, the shaders need {}", ctx.version, glx::MIN_VERSION).into());
+        }
         gl::Enable(gl::DEPTH_TEST);
+        let mut culling = opts.culling;
+        culling.apply();
+        if opts.srgb {
+            if fbconfig.srgb {
+                gl::Enable(gl::FRAMEBUFFER_SRGB);
+            } else {
+                println!("No sRGB-capable FBConfig, output stays linear");
+            }
+        }
 
-        let program = shader::create_program();
-        shader::set_current_program(program);
+        let samples = fbconfig.samples;
+        let mut msaa_target = None;
+        if opts.msaa_samples > 1 && samples < 2 {
+            match msaa::MultisampleTarget::new(width, height, opts.msaa_samples) {
+                Ok(target) => msaa_target = Some(target),
+                Err(e) => println!("MSAA disabled: {e}"),
+            }
+        } else if samples > 1 {
+            gl::Enable(gl::MULTISAMPLE);
+            println!("Window visual has {samples}x MSAA");
+        }
 
-        let shapes: Vec<Box<dyn Shape>> = vec![
-            Box::new(Cube::new()),
-            Box::new(Cube::new()),
-            Box::new(Pyramid::new()),
-            Box::new(Sphere::new()),
-        ];
+        let mut pacing = opts.pacing.apply(dpy, window, opts.fps_cap);
+
+        let mut mouse = pointer::MouseCapture::new(dpy, window);
+        mouse.resize(width, height);
+        if opts.capture_mouse {
+            mouse.capture();
+        }
 
-        let model_loc = gl::GetUniformLocation(program, b"model\0".as_ptr() as *const _);
-        let view_loc = gl::GetUniformLocation(program, b"view\0".as_ptr() as *const _);
-        let proj_loc = gl::GetUniformLocation(program, b"projection\0".as_ptr() as *const _);
-        let colourmode_loc = gl::GetUniformLocation(program, b"colourmode\0".as_ptr() as *const _);
+        let mut renderer = Renderer::new(opts.shadow);
+        let mut cam = scene_file.camera.to_camera();
+        let mut demo = DemoScene::new(scene_file);
+        let mut watcher = opts.scene.as_deref().map(SceneWatcher::new);
 
         println!("Entering main loop... (Press Escape to exit)");
 
-        let mut cam = Camera::default();
-        let mut angle = Vec3::splat(0.0);
-        let mut scale = Vec3::splat(1.0);
-        let mut trans = Vec3::splat(0.0);
-        let mut rotation_speed: f32 = 0.0;
+        let mut state = SceneState {
+            colourmode: opts.colourmode,
+            only: opts.shape,
+            show_back_faces: opts.show_back_faces,
+            lighting: !demo.scene.lights.is_empty(),
+            shading: opts.shading,
+            attenuation: opts.attenuation,
+            shadows: opts.shadows,
+            shadow_map_view: opts.show_shadow_map.then_some(0),
+            ..SceneState::default()
+        };
         let mut last_time = std::time::Instant::now();
         let mut mouse_held = false;
-        let mut mouse_x_abs = i16::MIN;
-        let mut mouse_y_abs = i16::MIN;
-        let mut colourmode: GLuint = 0;
-        let mut pyramid_time: f32 = 0.0;
+        let mut last_mouse: Option<(i32, i32)> = None;
+        let mut screenshot_requested = false;
+        let mut recorder: Option<recording::Recorder> = None;
+        let mut record_toggled = opts.record.start;
 
-        loop {
+        'frames: loop {
             while x11::XPending(dpy) != 0 {
                 let mut ev: x11::XEvent = std::mem::zeroed();
                 x11::XNextEvent(dpy, &mut ev);
@@ -84,28 +153,128 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 match ev.into_event() {
                     x11::Event::KeyPress(key_ev) => {
                         match key_ev.keycode {
-                            9 => return Ok(()),   // Escape
+                            9 => {
+                                // Escape
+                                break 'frames;
+                            }
                             25 => cam.z -= 0.01,  // W
                             39 => cam.z += 0.01,  // S
                             38 => cam.x -= 0.01,  // A
                             40 => cam.x += 0.01,  // D
-                            53 => scale.x += 0.1,
-                            29 => scale.y += 0.1,
-                            52 => scale.z += 0.1,
-                            111 => trans.y += 0.1, // Up
-                            116 => trans.y -= 0.1, // Down
-                            113 => trans.x -= 0.1, // Left
-                            114 => trans.x += 0.1, // Right
-                            48 => rotation_speed += 0.1,
-                            51 => rotation_speed -= 0.1,
+                            53 => state.scale.x += 0.1,
+                            29 => state.scale.y += 0.1,
+                            52 => state.scale.z += 0.1,
+                            111 => state.trans.y += 0.1, // Up
+                            116 => state.trans.y -= 0.1, // Down
+                            113 => state.trans.x -= 0.1, // Left
+                            114 => state.trans.x += 0.1, // Right
+                            48 => state.rotation_speed += 0.1,
+                            51 => state.rotation_speed -= 0.1,
                             27 => {
-                                cam = Camera::default();
-                                angle = Vec3::splat(0.0);
-                                scale = Vec3::splat(1.0);
-                                trans = Vec3::splat(0.0);
-                                rotation_speed = 0.0;
+                                cam = demo.file.camera.to_camera();
+                                state.reset();
+                            }
+                            71 => {
+                                // F5
+                                let mut file = demo.file.clone();
+                                file.camera = CameraDesc::from_camera(&cam);
+                                file.lights = demo.scene.lights.clone();
+                                let watcher = watcher.get_or_insert_with(|| SceneWatcher::new(Path::new(DEFAULT_SCENE_PATH)));
+                                match watcher.save(&file) {
+                                    Ok(()) => {
+                                        println!("Saved scene to {}", watcher.path.display());
+                                        demo.file = file;
+                                    }
+                                    Err(e) => println!("Saving {} failed: {e}", watcher.path.display()),
+                                }
+                            }
+                            54 => state.colourmode = 1 - state.colourmode,
+                            45 => {
+                                // K
+                                culling.face = culling.face.next();
+                                culling.apply();
+                                println!("Face culling: {culling}");
+                            }
+                            56 => state.show_back_faces = !state.show_back_faces, // B
+                            46 => {
+                                // L
+                                state.lighting = !state.lighting;
+                                println!("Lighting {}", if state.lighting { "on" } else { "off" });
+                            }
+                            33 | 32 => {
+                                // P drops a point light where the camera is, O
+                                // a spot light shining where it looks
+                                let light = if key_ev.keycode == 33 {
+                                    Light::point(cam.position(), 5.0)
+                                } else {
+                                    Light::spot(cam.position(), cam.front(), 10.0)
+                                };
+                                if demo.scene.lights.len() < MAX_LIGHTS {
+                                    demo.scene.lights.push(light);
+                                    state.lighting = true;
+                                    println!("Added a {:?} light, {} in the scene", light.kind, demo.scene.lights.len());
+                                } else {
+                                    println!("Already at the limit of {MAX_LIGHTS} lights");
+                                }
+                            }
+                            43 => {
+                                // H
+                                state.shading = state.shading.next();
+                                println!("{} shading", state.shading);
+                            }
+                            44 => {
+                                // J
+                                state.attenuation = !state.attenuation;
+                                println!("Light attenuation {}", if state.attenuation { "on" } else { "off" });
+                            }
+                            57 => {
+                                // N
+                                state.shadows = !state.shadows;
+                                println!("Shadows {}", if state.shadows { "on" } else { "off" });
+                            }
+                            58 => {
+                                // M: off, then each shadow map in turn
+                                let layers = renderer.shadows.as_ref().map_or(0, |shadows| shadows.layers());
+                                state.shadow_map_view = match state.shadow_map_view {
+                                    None if layers > 0 => Some(0),
+                                    Some(layer) if layer + 1 < layers => Some(layer + 1),
+                                    _ => None,
+                                };
+                                match state.shadow_map_view {
+                                    Some(layer) => println!("Showing shadow map {} of {layers}", layer + 1),
+                                    None => println!("Shadow map view off"),
+                                }
+                            }
+                            119 => {
+                                // Delete
+                                if demo.scene.lights.pop().is_some() {
+                                    println!("Removed a light, {} left", demo.scene.lights.len());
+                                }
+                            }
+                            95 => {
+                                // F11
+                                mode.fullscreen = !mode.fullscreen;
+                                window::set_fullscreen(dpy, window, mode.fullscreen);
+                            }
+                            76 => {
+                                // F10
+                                mode.borderless = !mode.borderless;
+                                window::set_borderless(dpy, window, mode.borderless);
+                            }
+                            96 => screenshot_requested = true, // F12
+                            75 => record_toggled = true,       // F9
+                            55 => {
+                                // V
+                                pacing = pacing.next(opts.fps_cap).apply(dpy, window, opts.fps_cap);
+                            }
+                            42 => {
+                                // G
+                                if mouse.is_captured() {
+                                    mouse.release();
+                                } else {
+                                    mouse.capture();
+                                }
                             }
-                            54 => colourmode = 1 - colourmode,
                             k => println!("Keycode: {}", k),
                         }
                     }
@@ -114,30 +283,12 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                             1 => {
                                 if !mouse_held {
                                     mouse_held = true;
-                                    mouse_x_abs = i16::MIN;
+                                    last_mouse = None;
                                     println!("Mouse down!");
                                 }
                             }
-                            4 => {
-                                let front = Vec3::new(
-                                    cam.yaw.cos() * cam.pitch.cos(),
-                                    cam.pitch.sin(),
-                                    cam.yaw.sin() * cam.pitch.cos(),
-                                ).normalize();
-                                cam.x += front.x * 0.1;
-                                cam.y += front.y * 0.1;
-                                cam.z += front.z * 0.1;
-                            }
-                            5 => {
-                                let front = Vec3::new(
-                                    cam.yaw.cos() * cam.pitch.cos(),
-                                    cam.pitch.sin(),
-                                    cam.yaw.sin() * cam.pitch.cos(),
-                                ).normalize();
-                                cam.x -= front.x * 0.1;
-                                cam.y -= front.y * 0.1;
-                                cam.z -= front.z * 0.1;
-                            }
+                            4 => cam.advance(0.1),
+                            5 => cam.advance(-0.1),
                             b => println!("Button: {}", b),
                         }
                     }
@@ -153,24 +304,47 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                         }
                     }
                     x11::Event::Motion(motion_ev) => {
-                        if !mouse_held { continue; }
-                        if mouse_x_abs == i16::MIN {
-                            mouse_x_abs = motion_ev.x as i16;
-                            mouse_y_abs = motion_ev.y as i16;
+                        if mouse.is_captured() {
+                            if let Some((dx, dy)) = mouse.motion_delta(motion_ev) {
+                                cam.look(dx, dy);
+                            }
                             continue;
                         }
+                        if !mouse_held { continue; }
+                        let Some((last_x, last_y)) = last_mouse.replace((motion_ev.x, motion_ev.y)) else {
+                            continue;
+                        };
                         const SENSITIVITY: f32 = 0.001;
-                        let dx = -(motion_ev.x as i16 - mouse_x_abs) as f32;
-                        let dy = (motion_ev.y as i16 - mouse_y_abs) as f32;
+                        let dx = -(motion_ev.x - last_x) as f32;
+                        let dy = (motion_ev.y - last_y) as f32;
                         cam.yaw += dx * SENSITIVITY;
                         cam.pitch += dy * SENSITIVITY;
                         cam.pitch = cam.pitch.clamp(-1.5, 1.5);
-                        mouse_x_abs = motion_ev.x as i16;
-                        mouse_y_abs = motion_ev.y as i16;
+                    }
+                    x11::Event::Generic => {
+                        if let Some((dx, dy)) = mouse.raw_delta(&mut ev) {
+                            cam.look(dx, dy);
+                        }
                     }
                     x11::Event::DestroyNotify => {
                         println!("Window destroyed, exiting loop.");
-                        break;
+                        break 'frames;
+                    }
+                    x11::Event::Configure(conf_ev) => {
+                        if conf_ev.width > 0 && conf_ev.height > 0
+                            && (conf_ev.width as u16, conf_ev.height as u16) != (width, height)
+                        {
+                            width = conf_ev.width as u16;
+                            height = conf_ev.height as u16;
+                            gl::Viewport(0, 0, width as i32, height as i32);
+                            mouse.resize(width, height);
+                            if let Some(target) = &mut msaa_target
+                                && let Err(e) = target.resize(width, height)
+                            {
+                                println!("MSAA disabled: {e}");
+                                msaa_target = None;
+                            }
+                        }
                     }
                     x11::Event::Expose => {}
                     x11::Event::KeyRelease(_) => {}
@@ -183,79 +357,71 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 }
             }
             
+            if let Some(watcher) = &mut watcher
+                && let Some(result) = watcher.poll()
+            {
+                match result {
+                    Ok(file) => {
+                        // Only move the camera if the file moved it, so
+                        // editing nodes doesn't undo where you've flown to.
+                        if file.camera != demo.file.camera {
+                            cam = file.camera.to_camera();
+                        }
+                        demo = DemoScene::new(file);
+                        println!("Reloaded {}", watcher.path.display());
+                    }
+                    Err(e) => println!("Scene reload failed, keeping the current one: {e}"),
+                }
+            }
+
+            if record_toggled {
+                record_toggled = false;
+                match recorder.take() {
+                    Some(rec) => {
+                        rec.finish();
+                    }
+                    None => match recording::Recorder::start(&opts.record, opts.screenshot.format, opts.screenshot.alpha) {
+                        Ok(rec) => recorder = Some(rec),
+                        Err(e) => println!("Recording failed to start: {e}"),
+                    },
+                }
+            }
+
             let now = std::time::Instant::now();
             let delta = now.duration_since(last_time).as_secs_f32();
             last_time = now;
-            pyramid_time += delta;
-
-            angle.x += rotation_speed * delta;
-            angle.y += rotation_speed * delta;
-            angle.z += rotation_speed * delta;
-
-            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
-            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
-            gl::UseProgram(program);
+            // While recording, the scene runs on the recording's clock.
+            state.update(recorder.as_ref().map_or(delta, |rec| rec.timestep()));
+            demo.update(&state);
 
-            if colourmode_loc != -1 {
-                gl::Uniform1ui(colourmode_loc, colourmode);
+            if let Some(target) = &msaa_target {
+                target.bind();
             }
 
-            if view_loc != -1 {
-                let front = Vec3::new(
-                    cam.yaw.cos() * cam.pitch.cos(),
-                    cam.pitch.sin(),
-                    cam.yaw.sin() * cam.pitch.cos(),
-                ).normalize();
-                let eye = Vec3::new(cam.x, cam.y, cam.z);
-                let center = eye + front;
-                let up = Vec3::new(0.0, 1.0, 0.0);
-                let view = Mat4::look_at_rh(eye, center, up);
-                gl::UniformMatrix4fv(view_loc, 1, gl::FALSE, view.to_cols_array().as_ptr());
-            }
+            renderer.draw(&demo.scene, &state, &cam, width, height);
 
-            let aspect = WIDTH as f32 / HEIGHT as f32;
-            let proj = Mat4::perspective_rh(45.0f32.to_radians(), aspect, 0.1, 10.0);
-            if proj_loc != -1 {
-                gl::UniformMatrix4fv(proj_loc, 1, gl::FALSE, proj.to_cols_array().as_ptr());
+            if let Some(target) = &msaa_target {
+                target.resolve(0);
             }
 
-            if model_loc != -1 {
-                // Cube 1
-                let mut shape_iter = shapes.iter();
-
-                // Cube 1
-                let model1 = Mat4::IDENTITY
-                    * Mat4::from_translation(trans)
-                    * Mat4::from_translation(Vec3::new(-0.5, 0.0, 0.0))
-                    * Mat4::from_scale(scale)
-                    * Mat4::from_rotation_x(-angle.x)
-                    * Mat4::from_rotation_y(angle.y)
-                    * Mat4::from_rotation_z(angle.z);
-                shape_iter.next().unwrap().draw(model1);
-
-                // Cube 2
-                let model2 = Mat4::IDENTITY
-                    * Mat4::from_translation(Vec3::new(0.5, 0.0, 0.0))
-                    * Mat4::from_scale(scale)
-                    * Mat4::from_rotation_x(-angle.x)
-                    * Mat4::from_rotation_y(angle.y)
-                    * Mat4::from_rotation_z(angle.z);
-                shape_iter.next().unwrap().draw(model2);
-
-                // Pyramid
-                let pyramid_y = pyramid_time.sin() * 0.5;
-                let pyramid_model = Mat4::from_translation(Vec3::new(0.0, pyramid_y, -1.0))
-                    * Mat4::from_rotation_y(pyramid_time);
-                shape_iter.next().unwrap().draw(pyramid_model);
-
-                // Sphere
-                let sphere_model = Mat4::from_translation(Vec3::new(0.0, 0.0, -2.0))
-                    * Mat4::from_scale(Vec3::splat(0.7));
-                shape_iter.next().unwrap().draw(sphere_model);
+            if screenshot_requested {
+                screenshot_requested = false;
+                match screenshot::save(width, height, &opts.screenshot) {
+                    Ok(path) => println!("Saved screenshot to {}", path.display()),
+                    Err(e) => println!("Screenshot failed: {e}"),
+                }
+            }
+            if let Some(rec) = &mut recorder {
+                rec.capture(0, width, height);
             }
 
             glx::glXSwapBuffers(dpy, window);
-            std::thread::sleep(FRAME_TIME.saturating_sub(now.elapsed()));
+            pacing.wait(now);
+        }
+        // Collects the readbacks still in flight and joins the encoders.
+        if let Some(rec) = recorder {
+            rec.finish();
         }
+        Ok(())
     }
 }
\ No newline at end of file
@@ -0,0 +1,307 @@
+//! Surface materials, set per draw as the `material` uniform struct from
+//! `shaders/material.glsl`, and a reader for Wavefront `.mtl` libraries.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+use crate::shader;
+use crate::texture::{TextureMap, Wrap};
+
+/// The texture unit `material.glsl`'s `diffusemap` sampler reads.
+pub const DIFFUSE_UNIT: u32 = 0;
+
+/// The texture unit `material.glsl`'s `normalmap` sampler reads.
+pub const NORMAL_UNIT: u32 = 1;
+
+/// Colours multiply the mesh's own vertex colours, so the default
+/// material leaves a shape looking as it always has.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Material {
+    /// Reflectance under the ambient light. Same as `diffuse` if unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ambient: Option<Vec3>,
+    pub diffuse: Vec3,
+    pub specular: Vec3,
+    /// Phong exponent: higher is a smaller, sharper highlight.
+    pub shininess: f32,
+    /// Light given off regardless of the scene's lights.
+    pub emissive: Vec3,
+    /// 1 is solid, 0 invisible.
+    pub opacity: f32,
+    /// Multiplies `diffuse` and `ambient`, and its alpha `opacity`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diffuse_map: Option<TextureMap>,
+    /// Tangent-space normals, +Z out of the surface, bending the lighting
+    /// of every model except Gouraud.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub normal_map: Option<TextureMap>,
+    /// One of the scene file's screens, whose picture replaces
+    /// `diffuse_map`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub screen: Option<String>,
+    /// How much of the scene's skybox is mirrored in the surface, 0 to 1.
+    pub reflectivity: f32,
+    /// How much of the skybox shows through the surface, bent by `ior`,
+    /// 0 to 1. Reflection goes on top.
+    pub refraction: f32,
+    /// Index of refraction: 1 passes light straight through, glass is
+    /// about 1.5.
+    pub ior: f32,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            ambient: None,
+            diffuse: Vec3::ONE,
+            specular: Vec3::new(1.0, 0.8, 0.6),
+            shininess: 8.0,
+            emissive: Vec3::ZERO,
+            opacity: 1.0,
+            diffuse_map: None,
+            normal_map: None,
+            screen: None,
+            reflectivity: 0.0,
+            refraction: 0.0,
+            ior: 1.5,
+        }
+    }
+}
+
+impl Material {
+    pub fn ambient(&self) -> Vec3 {
+        self.ambient.unwrap_or(self.diffuse)
+    }
+
+    pub fn is_transparent(&self) -> bool {
+        self.opacity < 1.0
+    }
+
+    pub fn check(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.opacity) {
+            return Err(format!("opacity {} is outside 0 to 1", self.opacity));
+        }
+        if self.shininess < 0.0 {
+            return Err("shininess can't be negative".into());
+        }
+        for (name, value) in [("reflectivity", self.reflectivity), ("refraction", self.refraction)] {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!("{name} {value} is outside 0 to 1"));
+            }
+        }
+        if self.ior < 1.0 {
+            return Err(format!("index of refraction {} is below 1", self.ior));
+        }
+        Ok(())
+    }
+
+    /// Sets the `material` uniforms on the current program, skipping any
+    /// it doesn't use. Whether the maps are there to sample is up to the
+    /// caller, who binds them to `DIFFUSE_UNIT` and `NORMAL_UNIT`.
+    pub fn upload(&self, diffuse_mapped: bool, normal_mapped: bool) {
+        let program = shader::get_current_program();
+        let vec3s = [
+            (c"material.ambient", self.ambient()),
+            (c"material.diffuse", self.diffuse),
+            (c"material.specular", self.specular),
+            (c"material.emissive", self.emissive),
+        ];
+        let floats = [
+            (c"material.shininess", self.shininess),
+            (c"material.opacity", self.opacity),
+            (c"material.reflectivity", self.reflectivity),
+            (c"material.refraction", self.refraction),
+            (c"material.ior", self.ior),
+        ];
+        unsafe {
+            for (name, value) in vec3s {
+                let loc = gl::GetUniformLocation(program, name.as_ptr() as *const _);
+                if loc != -1 {
+                    gl::Uniform3f(loc, value.x, value.y, value.z);
+                }
+            }
+            for (name, value) in floats {
+                let loc = gl::GetUniformLocation(program, name.as_ptr() as *const _);
+                if loc != -1 {
+                    gl::Uniform1f(loc, value);
+                }
+            }
+            let ints = [
+                (c"material.diffuse_mapped", diffuse_mapped as i32),
+                (c"material.normal_mapped", normal_mapped as i32),
+                (c"diffusemap", DIFFUSE_UNIT as i32),
+                (c"normalmap", NORMAL_UNIT as i32),
+            ];
+            for (name, value) in ints {
+                let loc = gl::GetUniformLocation(program, name.as_ptr() as *const _);
+                if loc != -1 {
+                    gl::Uniform1i(loc, value);
+                }
+            }
+        }
+    }
+}
+
+/// Reads the materials in an `.mtl` file: `Ka`, `Kd`, `Ks`, `Ke`, `Ns`,
+/// `Ni`, `d` or `Tr`, `map_Kd`, and `norm` with its common stand-ins `map_Bump`
+/// and `bump`, which are taken to be normal maps too. Of the texture
+/// options only `-clamp` is kept, and paths are left as written, relative
+/// to the file. Anything else, such as `illum` or other maps, is skipped.
+pub fn parse_mtl(source: &str) -> Result<BTreeMap<String, Material>, String> {
+    let mut materials = BTreeMap::new();
+    let mut current: Option<(String, Material)> = None;
+    for (number, line) in source.lines().enumerate() {
+        let line = line.split('#').next().unwrap_or("").trim();
+        let mut words = line.split_whitespace();
+        let Some(keyword) = words.next() else {
+            continue;
+        };
+        let args: Vec<&str> = words.collect();
+        let at = |e: String| format!("line {}: {e}", number + 1);
+
+        if keyword == "newmtl" {
+            if args.is_empty() {
+                return Err(at("newmtl without a name".into()));
+            }
+            materials.extend(current.take());
+            current = Some((args.join(" "), Material::default()));
+            continue;
+        }
+        let Some((_, material)) = &mut current else {
+            return Err(at(format!("'{keyword}' before any newmtl")));
+        };
+        match keyword {
+            "Ka" => material.ambient = Some(colour(&args).map_err(at)?),
+            "Kd" => material.diffuse = colour(&args).map_err(at)?,
+            "Ks" => material.specular = colour(&args).map_err(at)?,
+            "Ke" => material.emissive = colour(&args).map_err(at)?,
+            "Ns" => material.shininess = number_arg(&args).map_err(at)?,
+            "Ni" => material.ior = number_arg(&args).map_err(at)?,
+            "d" => material.opacity = number_arg(&args).map_err(at)?,
+            "Tr" => material.opacity = 1.0 - number_arg(&args).map_err(at)?,
+            "map_Kd" => material.diffuse_map = Some(texture_map(&args).map_err(at)?),
+            "norm" | "map_Bump" | "bump" => material.normal_map = Some(texture_map(&args).map_err(at)?),
+            _ => {}
+        }
+    }
+    materials.extend(current);
+    for (name, material) in &materials {
+        material.check().map_err(|e| format!("material '{name}': {e}"))?;
+    }
+    Ok(materials)
+}
+
+pub fn load_mtl(path: &Path) -> Result<BTreeMap<String, Material>, String> {
+    let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    parse_mtl(&source).map_err(|e| format!("{}: {e}", path.display()))
+}
+
+fn number_arg(args: &[&str]) -> Result<f32, String> {
+    match args {
+        [value] => value.parse().map_err(|_| format!("'{value}' isn't a number")),
+        _ => Err(format!("expected one number, got {}", args.len())),
+    }
+}
+
+/// `[-option value...] file`. Of the options only `-clamp` means anything
+/// here; the rest are skipped along with their values.
+fn texture_map(args: &[&str]) -> Result<TextureMap, String> {
+    let mut wrap = Wrap::Repeat;
+    let mut rest = args;
+    while let [option, tail @ ..] = rest
+        && option.starts_with('-')
+    {
+        // -o, -s and -t take up to three numbers, -mm two, the rest one.
+        let values = match *option {
+            "-o" | "-s" | "-t" => tail.iter().take(3).take_while(|v| v.parse::<f32>().is_ok()).count(),
+            "-mm" => 2,
+            _ => 1,
+        };
+        if tail.len() < values {
+            return Err(format!("'{option}' is missing its value"));
+        }
+        if *option == "-clamp" {
+            wrap = if tail[0] == "on" { Wrap::ClampToEdge } else { Wrap::Repeat };
+        }
+        rest = &tail[values..];
+    }
+    if rest.is_empty() {
+        return Err("texture map without a file".into());
+    }
+    Ok(TextureMap {
+        wrap,
+        ..TextureMap::new(rest.join(" "))
+    })
+}
+
+/// `r g b`, or just `r` for a grey. The `spectral` and `xyz` forms aren't
+/// supported.
+fn colour(args: &[&str]) -> Result<Vec3, String> {
+    let values = args
+        .iter()
+        .map(|value| value.parse::<f32>().map_err(|_| format!("'{value}' isn't a number")))
+        .collect::<Result<Vec<_>, _>>()?;
+    match values[..] {
+        [grey] => Ok(Vec3::splat(grey)),
+        [r, g, b] => Ok(Vec3::new(r, g, b)),
+        _ => Err(format!("expected an RGB colour, got {} values", values.len())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIBRARY: &str = "
+        # Two materials
+        newmtl brass
+        Ka 0.33 0.22 0.03
+        Kd 0.78 0.57 0.11
+        Ks 0.99 0.94 0.81
+        Ns 27.9
+        illum 2
+
+        newmtl frosted glass
+        Kd 0.9
+        Tr 0.75
+        Ni 1.45
+        map_Kd glass.png
+    ";
+
+    #[test]
+    fn mtl_materials_are_read() {
+        let materials = parse_mtl(LIBRARY).unwrap();
+        assert_eq!(materials.len(), 2);
+        let brass = &materials["brass"];
+        assert_eq!(brass.ambient(), Vec3::new(0.33, 0.22, 0.03));
+        assert_eq!(brass.shininess, 27.9);
+        assert!(!brass.is_transparent());
+
+        let glass = &materials["frosted glass"];
+        assert_eq!((glass.diffuse, glass.ambient()), (Vec3::splat(0.9), Vec3::splat(0.9)));
+        assert_eq!((glass.opacity, glass.ior), (0.25, 1.45));
+        assert!(glass.is_transparent());
+        assert_eq!(glass.diffuse_map, Some(TextureMap::new("glass.png")));
+    }
+
+    #[test]
+    fn mtl_map_options_are_skipped_except_clamp() {
+        let materials = parse_mtl("newmtl a\nmap_Kd -s 2 2 -clamp on -mm 0 1 my tex.jpg\nmap_Bump -bm 0.5 n.png\n").unwrap();
+        let map = materials["a"].diffuse_map.clone().unwrap();
+        assert_eq!((map.path, map.wrap), ("my tex.jpg".into(), Wrap::ClampToEdge));
+        assert_eq!(materials["a"].normal_map, Some(TextureMap::new("n.png")));
+        assert!(parse_mtl("newmtl a\nmap_Kd -clamp\n").is_err());
+    }
+
+    #[test]
+    fn mtl_errors_give_the_line() {
+        let err = parse_mtl("newmtl a\nKd 1 0\n").unwrap_err();
+        assert!(err.starts_with("line 2:"), "{err}");
+        assert!(parse_mtl("Kd 1 1 1\n").is_err());
+        assert!(parse_mtl("newmtl a\nd 2\n").unwrap_err().contains("opacity"));
+    }
+}
@@ -0,0 +1,48 @@
+use gl::types::{GLint, GLuint};
+
+use crate::framebuffer::{Framebuffer, FramebufferDesc, Storage};
+
+/// Off-screen multisampled colour and depth buffers that the scene renders
+/// into, resolved onto the default framebuffer with a blit. Unlike MSAA
+/// through the FBConfig this works whatever visual the window ended up with.
+pub struct MultisampleTarget {
+    framebuffer: Framebuffer,
+}
+
+impl MultisampleTarget {
+    /// `samples` is clamped to `GL_MAX_SAMPLES`.
+    pub fn new(width: u16, height: u16, samples: u32) -> Result<Self, String> {
+        let mut max_samples: GLint = 0;
+        unsafe {
+            gl::GetIntegerv(gl::MAX_SAMPLES, &mut max_samples);
+        }
+        let samples = (samples as i32).min(max_samples);
+        if samples < 2 {
+            return Err(format!("multisampled renderbuffers unsupported (GL_MAX_SAMPLES = {max_samples})"));
+        }
+        let desc = FramebufferDesc {
+            colour: Some(Storage::Renderbuffer),
+            depth: Some(Storage::Renderbuffer),
+            samples: samples as u32,
+        };
+        let framebuffer = Framebuffer::new(width, height, desc, "MSAA framebuffer")?;
+        println!("Rendering through a {samples}x multisampled framebuffer");
+        Ok(Self { framebuffer })
+    }
+
+    /// Reallocates storage at the new size; a no-op if nothing changed.
+    pub fn resize(&mut self, width: u16, height: u16) -> Result<(), String> {
+        self.framebuffer.resize(width, height)
+    }
+
+    /// Directs subsequent drawing into the multisampled buffers.
+    pub fn bind(&self) {
+        self.framebuffer.bind();
+    }
+
+    /// Resolves the samples into framebuffer `target`: 0 for the window,
+    /// ready to swap, or an off-screen target when running headless.
+    pub fn resolve(&self, target: GLuint) {
+        self.framebuffer.blit_to(target);
+    }
+}
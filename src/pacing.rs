@@ -0,0 +1,93 @@
+use std::os::raw::c_void;
+use std::time::{Duration, Instant};
+
+use crate::{glx, x11};
+
+pub const DEFAULT_FPS_CAP: u32 = 60;
+
+/// How the main loop paces frames.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FramePacing {
+    /// Block in `glXSwapBuffers` until vblank. Adaptive vsync lets a late
+    /// frame tear instead of waiting a whole extra refresh.
+    VSync { adaptive: bool },
+    /// Swap immediately and sleep off the rest of the frame budget.
+    Capped(u32),
+    Uncapped,
+}
+
+impl Default for FramePacing {
+    fn default() -> Self {
+        FramePacing::VSync { adaptive: false }
+    }
+}
+
+impl FramePacing {
+    /// Sets the swap interval for this mode. If the driver can't do vsync we
+    /// fall back to capping at `cap` fps, so the returned mode is the one in
+    /// effect.
+    ///
+    /// # Safety
+    ///
+    /// As for `glx::set_swap_interval`.
+    pub unsafe fn apply(self, dpy: *mut c_void, window: x11::Window, cap: u32) -> FramePacing {
+        let interval = match self {
+            FramePacing::VSync { adaptive: true } => -1,
+            FramePacing::VSync { adaptive: false } => 1,
+            FramePacing::Capped(_) | FramePacing::Uncapped => 0,
+        };
+
+        match unsafe { glx::set_swap_interval(dpy, window, interval) } {
+            Ok(ext) => {
+                println!("Frame pacing: {self} (via {ext})");
+                self
+            }
+            Err(e) => match self {
+                FramePacing::VSync { adaptive: true } => {
+                    println!("Adaptive vsync unavailable: {e}");
+                    unsafe { FramePacing::VSync { adaptive: false }.apply(dpy, window, cap) }
+                }
+                FramePacing::VSync { adaptive: false } => {
+                    println!("VSync unavailable: {e}");
+                    let fallback = FramePacing::Capped(cap);
+                    println!("Frame pacing: {fallback}");
+                    fallback
+                }
+                // Not being able to turn vsync off is not worth failing over;
+                // the cap still applies on top of whatever the driver does.
+                _ => {
+                    println!("Frame pacing: {self} (swap interval unchanged: {e})");
+                    self
+                }
+            },
+        }
+    }
+
+    /// The mode the runtime toggle switches to next.
+    pub fn next(self, cap: u32) -> FramePacing {
+        match self {
+            FramePacing::VSync { .. } => FramePacing::Capped(cap),
+            FramePacing::Capped(_) => FramePacing::Uncapped,
+            FramePacing::Uncapped => FramePacing::VSync { adaptive: false },
+        }
+    }
+
+    /// Sleeps out the remainder of the frame in capped mode.
+    pub fn wait(self, frame_start: Instant) {
+        if let FramePacing::Capped(fps) = self {
+            let frame_time = Duration::from_nanos(1_000_000_000 / fps.max(1) as u64);
+            std::thread::sleep(frame_time.saturating_sub(frame_start.elapsed()));
+        }
+    }
+}
+
+impl std::fmt::Display for FramePacing {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FramePacing::VSync { adaptive: true } => write!(f, "adaptive vsync"),
+            FramePacing::VSync { adaptive: false } => write!(f, "vsync"),
+            FramePacing::Capped(fps) => write!(f, "capped at {fps} fps"),
+            FramePacing::Uncapped => write!(f, "uncapped"),
+        }
+    }
+}
@@ -0,0 +1,140 @@
+use std::os::raw::{c_uint, c_void};
+
+use crate::{x11, xinput::XInput2};
+
+/// Captured-mouse mode for first-person camera control: the pointer is
+/// grabbed, hidden and confined to the window, and motion is reported as
+/// relative deltas instead of absolute positions.
+///
+/// Deltas come from XInput2 raw motion when the server supports it. Without
+/// it we fall back to warping the pointer back to the window centre after
+/// every motion event and measuring how far it moved.
+pub struct MouseCapture {
+    dpy: *mut c_void,
+    window: x11::Window,
+    blank_cursor: x11::Cursor,
+    xinput: Option<XInput2>,
+    centre: (i32, i32),
+    captured: bool,
+}
+
+impl MouseCapture {
+    /// # Safety
+    ///
+    /// `dpy` must be an open display and `window` one of its windows, both outliving the capture.
+    pub unsafe fn new(dpy: *mut c_void, window: x11::Window) -> Self {
+        unsafe {
+            let data = [0u8; 8];
+            let bitmap = x11::XCreateBitmapFromData(dpy, window, data.as_ptr() as _, 8, 8);
+            let mut black = x11::XColor::default();
+            let blank_cursor = x11::XCreatePixmapCursor(dpy, bitmap, bitmap, &mut black, &mut black, 0, 0);
+            x11::XFreePixmap(dpy, bitmap);
+
+            let xinput = XInput2::load(dpy);
+            match &xinput {
+                Some(xi) => xi.select_raw_motion(dpy),
+                None => println!("XInput2 raw motion unavailable, falling back to pointer warping"),
+            }
+
+            Self {
+                dpy,
+                window,
+                blank_cursor,
+                xinput,
+                centre: (0, 0),
+                captured: false,
+            }
+        }
+    }
+
+    pub fn is_captured(&self) -> bool {
+        self.captured
+    }
+
+    /// Keeps the warp target in the middle of the window after a resize.
+    pub fn resize(&mut self, width: u16, height: u16) {
+        self.centre = (width as i32 / 2, height as i32 / 2);
+    }
+
+    pub fn capture(&mut self) -> bool {
+        if self.captured {
+            return true;
+        }
+        unsafe {
+            let mask = (x11::BUTTON_PRESS_MASK | x11::BUTTON_RELEASE_MASK | x11::POINTER_MOTION_MASK) as c_uint;
+            let ret = x11::XGrabPointer(
+                self.dpy,
+                self.window,
+                1,
+                mask,
+                x11::GRAB_MODE_ASYNC,
+                x11::GRAB_MODE_ASYNC,
+                self.window,
+                self.blank_cursor,
+                x11::CURRENT_TIME,
+            );
+            if ret != x11::GRAB_SUCCESS {
+                println!("Pointer grab failed ({ret})");
+                return false;
+            }
+            x11::XDefineCursor(self.dpy, self.window, self.blank_cursor);
+            self.warp_to_centre();
+        }
+        self.captured = true;
+        true
+    }
+
+    pub fn release(&mut self) {
+        if !self.captured {
+            return;
+        }
+        unsafe {
+            x11::XUngrabPointer(self.dpy, x11::CURRENT_TIME);
+            x11::XUndefineCursor(self.dpy, self.window);
+            x11::XFlush(self.dpy);
+        }
+        self.captured = false;
+    }
+
+    /// Relative motion from a generic (XInput2) event while captured.
+    pub fn raw_delta(&self, ev: &mut x11::XEvent) -> Option<(f32, f32)> {
+        if !self.captured {
+            return None;
+        }
+        // `new` was promised a display that outlives this.
+        let (dx, dy) = unsafe { self.xinput.as_ref()?.raw_motion_delta(self.dpy, ev)? };
+        Some((dx as f32, dy as f32))
+    }
+
+    /// Relative motion from a core motion event while captured. Only used
+    /// when XInput2 is missing; otherwise raw events carry the deltas.
+    pub fn motion_delta(&self, motion: &x11::XMotionEvent) -> Option<(f32, f32)> {
+        if !self.captured || self.xinput.is_some() {
+            return None;
+        }
+        let dx = motion.x - self.centre.0;
+        let dy = motion.y - self.centre.1;
+        if dx == 0 && dy == 0 {
+            // The event generated by our own warp.
+            return None;
+        }
+        self.warp_to_centre();
+        Some((dx as f32, dy as f32))
+    }
+
+    fn warp_to_centre(&self) {
+        unsafe {
+            x11::XWarpPointer(self.dpy, 0, self.window, 0, 0, 0, 0, self.centre.0, self.centre.1);
+            x11::XFlush(self.dpy);
+        }
+    }
+}
+
+impl Drop for MouseCapture {
+    fn drop(&mut self) {
+        self.release();
+        unsafe {
+            x11::XFreeCursor(self.dpy, self.blank_cursor);
+        }
+    }
+}
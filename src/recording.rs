@@ -0,0 +1,240 @@
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::SystemTime;
+
+use gl::types::{GLsync, GLuint};
+
+use crate::image::Image;
+use crate::screenshot::{self, Format};
+
+pub const DEFAULT_RECORD_FPS: u32 = 60;
+
+/// Readbacks in flight. Frame N is mapped while frames N+1 and N+2 are
+/// still being drawn, by which time the copy into its PBO has finished.
+const PBO_COUNT: usize = 3;
+
+/// Encoded frames allowed to queue up before the main loop waits for the
+/// encoders. Since time is simulated this only slows recording down, it
+/// never drops or stutters frames.
+const QUEUE_DEPTH: usize = 8;
+
+#[derive(Debug, Clone)]
+pub struct RecordOptions {
+    /// Each recording gets its own timestamped directory in here.
+    pub dir: PathBuf,
+    /// Simulated frames per second. Every recorded frame advances the
+    /// scene by exactly `1 / fps` seconds, however long it took to render.
+    pub fps: u32,
+    /// Start recording as soon as the first frame is drawn.
+    pub start: bool,
+}
+
+impl Default for RecordOptions {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("recordings"),
+            fps: DEFAULT_RECORD_FPS,
+            start: false,
+        }
+    }
+}
+
+struct Frame {
+    index: u32,
+    image: Image,
+}
+
+/// One pixel pack buffer and the readback waiting in it, if any.
+struct Slot {
+    pbo: GLuint,
+    size: (u16, u16),
+    pending: Option<(u32, GLsync)>,
+}
+
+/// Dumps every frame to `frame-000000.png`, `frame-000001.png`, ... using
+/// asynchronous PBO readback and a pool of encoder threads, so neither the
+/// GPU copy nor PNG compression stalls the main loop.
+pub struct Recorder {
+    dir: PathBuf,
+    format: Format,
+    alpha: bool,
+    timestep: f32,
+    slots: Vec<Slot>,
+    next_slot: usize,
+    frames: u32,
+    sender: Option<SyncSender<Frame>>,
+    workers: Vec<JoinHandle<u32>>,
+}
+
+impl Recorder {
+    /// Creates a fresh directory under `opts.dir` for this recording. Frames
+    /// are written in the screenshot format, opaque unless `alpha` is set.
+    pub fn start(opts: &RecordOptions, format: Format, alpha: bool) -> std::io::Result<Self> {
+        let dir = opts.dir.join(format!("recording-{}", screenshot::timestamp(SystemTime::now())));
+        std::fs::create_dir_all(&dir)?;
+
+        let mut pbos = [0u32; PBO_COUNT];
+        unsafe {
+            gl::GenBuffers(PBO_COUNT as i32, pbos.as_mut_ptr());
+        }
+        let slots = pbos
+            .iter()
+            .map(|&pbo| Slot { pbo, size: (0, 0), pending: None })
+            .collect();
+
+        let (sender, receiver) = mpsc::sync_channel(QUEUE_DEPTH);
+        let receiver = Arc::new(Mutex::new(receiver));
+        let threads = std::thread::available_parallelism().map_or(2, |n| n.get().clamp(1, 4));
+        let workers = (0..threads)
+            .map(|_| {
+                let receiver = Arc::clone(&receiver);
+                let dir = dir.clone();
+                std::thread::spawn(move || encode_frames(&receiver, &dir, format, alpha))
+            })
+            .collect();
+
+        println!("Recording to {} at {} fps", dir.display(), opts.fps);
+        Ok(Self {
+            dir,
+            format,
+            alpha,
+            timestep: 1.0 / opts.fps as f32,
+            slots,
+            next_slot: 0,
+            frames: 0,
+            sender: Some(sender),
+            workers,
+        })
+    }
+
+    /// How far to advance the scene per recorded frame, in place of the
+    /// wall-clock delta.
+    pub fn timestep(&self) -> f32 {
+        self.timestep
+    }
+
+    /// Queues a readback of framebuffer `fbo` (0 for the back buffer) and
+    /// hands the oldest finished readback to the encoders. Call it where a
+    /// screenshot would be taken: after resolving, before swapping.
+    pub fn capture(&mut self, fbo: GLuint, width: u16, height: u16) {
+        let slot = self.next_slot;
+        self.next_slot = (self.next_slot + 1) % PBO_COUNT;
+        // Reusing the oldest slot, so its frame has had the longest to land.
+        self.collect(slot);
+
+        let Slot { pbo, size, pending } = &mut self.slots[slot];
+        unsafe {
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+            if fbo == 0 {
+                gl::ReadBuffer(gl::BACK);
+            }
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, *pbo);
+            if *size != (width, height) {
+                *size = (width, height);
+                let bytes = width as isize * height as isize * 4;
+                gl::BufferData(gl::PIXEL_PACK_BUFFER, bytes, std::ptr::null(), gl::STREAM_READ);
+            }
+            gl::PixelStorei(gl::PACK_ALIGNMENT, 1);
+            // With a pack buffer bound the pointer is an offset into it and
+            // the call returns without waiting for the GPU.
+            gl::ReadPixels(0, 0, width as i32, height as i32, gl::RGBA, gl::UNSIGNED_BYTE, std::ptr::null_mut());
+            *pending = Some((self.frames, gl::FenceSync(gl::SYNC_GPU_COMMANDS_COMPLETE, 0)));
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+            gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+        }
+        self.frames += 1;
+    }
+
+    /// Maps the slot's finished readback, if it has one, and sends it off.
+    fn collect(&mut self, slot: usize) {
+        let Slot { pbo, size: (width, height), pending } = &mut self.slots[slot];
+        let Some((index, fence)) = pending.take() else {
+            return;
+        };
+
+        let len = *width as usize * *height as usize * 4;
+        let mut pixels = vec![0u8; len];
+        unsafe {
+            // Normally already signalled, this only blocks if the GPU is
+            // more than PBO_COUNT frames behind.
+            gl::ClientWaitSync(fence, gl::SYNC_FLUSH_COMMANDS_BIT, u64::MAX);
+            gl::DeleteSync(fence);
+
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, *pbo);
+            let mapped = gl::MapBufferRange(gl::PIXEL_PACK_BUFFER, 0, len as isize, gl::MAP_READ_BIT) as *const u8;
+            if mapped.is_null() {
+                println!("Recording: couldn't map the readback for frame {index}, skipping it");
+                gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+                return;
+            }
+            std::ptr::copy_nonoverlapping(mapped, pixels.as_mut_ptr(), len);
+            gl::UnmapBuffer(gl::PIXEL_PACK_BUFFER);
+            gl::BindBuffer(gl::PIXEL_PACK_BUFFER, 0);
+        }
+
+        let image = Image {
+            width: *width as u32,
+            height: *height as u32,
+            pixels,
+        };
+        if let Some(sender) = &self.sender {
+            // Only fails if every encoder died, which they report themselves.
+            let _ = sender.send(Frame { index, image });
+        }
+    }
+
+    /// Collects the outstanding readbacks, waits for the encoders to
+    /// drain and frees the PBOs. Returns the directory written to.
+    pub fn finish(mut self) -> PathBuf {
+        for i in 0..PBO_COUNT {
+            self.collect((self.next_slot + i) % PBO_COUNT);
+        }
+        self.sender = None;
+        let written: u32 = self.workers.drain(..).map(|worker| worker.join().unwrap_or(0)).sum();
+        println!(
+            "Recorded {} of {} frames to {} ({}{})",
+            written,
+            self.frames,
+            self.dir.display(),
+            self.format.extension(),
+            if self.alpha { ", with alpha" } else { "" },
+        );
+        std::mem::take(&mut self.dir)
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        unsafe {
+            for slot in &self.slots {
+                if let Some((_, fence)) = slot.pending {
+                    gl::DeleteSync(fence);
+                }
+                gl::DeleteBuffers(1, &slot.pbo);
+            }
+        }
+    }
+}
+
+/// Encoder thread body. Returns how many frames it wrote.
+fn encode_frames(receiver: &Mutex<Receiver<Frame>>, dir: &Path, format: Format, alpha: bool) -> u32 {
+    let mut written = 0;
+    loop {
+        // Hold the lock only to receive, not while encoding.
+        let frame = receiver.lock().unwrap().recv();
+        let Ok(Frame { index, mut image }) = frame else {
+            return written;
+        };
+        image.flip_vertically();
+        if !alpha {
+            image.make_opaque();
+        }
+        let path = dir.join(format!("frame-{index:06}.{}", format.extension()));
+        match screenshot::write(&image, &path, format, alpha) {
+            Ok(_) => written += 1,
+            Err(e) => println!("Recording: writing {} failed: {e}", path.display()),
+        }
+    }
+}
@@ -0,0 +1,357 @@
+use gl::types::{GLint, GLuint};
+use glam::{EulerRot, Quat, Vec3};
+
+use crate::animation::{self, Track};
+use crate::camera::Camera;
+use crate::light::LightBuffer;
+use crate::scene::{NodeId, Scene, Screen, Transform};
+use crate::scenefile::{SceneFile, ShapeDesc};
+use crate::shader;
+use crate::shading::ShadingModel;
+use crate::shadow::{ShadowMaps, ShadowSettings};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeKind {
+    Cube,
+    Pyramid,
+    Sphere,
+    Cylinder,
+}
+
+impl ShapeKind {
+    pub const ALL: [ShapeKind; 4] = [ShapeKind::Cube, ShapeKind::Pyramid, ShapeKind::Sphere, ShapeKind::Cylinder];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShapeKind::Cube => "cube",
+            ShapeKind::Pyramid => "pyramid",
+            ShapeKind::Sphere => "sphere",
+            ShapeKind::Cylinder => "cylinder",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.name() == name)
+    }
+
+    pub fn of(shape: ShapeDesc) -> Self {
+        match shape {
+            ShapeDesc::Cube => ShapeKind::Cube,
+            ShapeDesc::Pyramid => ShapeKind::Pyramid,
+            ShapeDesc::Sphere { .. } => ShapeKind::Sphere,
+            ShapeDesc::Cylinder { .. } => ShapeKind::Cylinder,
+        }
+    }
+}
+
+/// Everything the demo scene animates or the keyboard changes. Kept apart
+/// from the GL objects so the windowed loop and headless runs drive the
+/// same scene.
+pub struct SceneState {
+    pub angle: Vec3,
+    pub scale: Vec3,
+    pub trans: Vec3,
+    pub rotation_speed: f32,
+    pub colourmode: GLuint,
+    /// Seconds on the animation clock.
+    pub time: f32,
+    /// Draw only shapes of this kind, e.g. to render each one in isolation.
+    pub only: Option<ShapeKind>,
+    /// Debug view that hatches back faces in red.
+    pub show_back_faces: bool,
+    /// Shade with the scene's lights rather than flat vertex colours.
+    pub lighting: bool,
+    /// Which lit program to shade with.
+    pub shading: ShadingModel,
+    /// Whether point and spot lights dim with distance.
+    pub attenuation: bool,
+    /// Whether lights with shadow maps cast shadows.
+    pub shadows: bool,
+    /// Debug view showing this shadow map layer in a corner.
+    pub shadow_map_view: Option<usize>,
+}
+
+impl Default for SceneState {
+    fn default() -> Self {
+        Self {
+            angle: Vec3::splat(0.0),
+            scale: Vec3::splat(1.0),
+            trans: Vec3::splat(0.0),
+            rotation_speed: 0.0,
+            colourmode: 0,
+            time: 0.0,
+            only: None,
+            show_back_faces: false,
+            lighting: false,
+            shading: ShadingModel::default(),
+            attenuation: false,
+            shadows: true,
+            shadow_map_view: None,
+        }
+    }
+}
+
+impl SceneState {
+    pub fn shows(&self, kind: ShapeKind) -> bool {
+        self.only.is_none_or(|only| only == kind)
+    }
+
+    pub fn update(&mut self, delta: f32) {
+        self.time += delta;
+        self.angle += Vec3::splat(self.rotation_speed * delta);
+    }
+
+    /// Puts the transforms back to their defaults, keeping the display modes.
+    pub fn reset(&mut self) {
+        *self = Self {
+            colourmode: self.colourmode,
+            time: self.time,
+            only: self.only,
+            show_back_faces: self.show_back_faces,
+            lighting: self.lighting,
+            shading: self.shading,
+            attenuation: self.attenuation,
+            shadows: self.shadows,
+            shadow_map_view: self.shadow_map_view,
+            ..Self::default()
+        };
+    }
+}
+
+/// How the keyboard moves a node, picked by name for the demo's cubes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum KeyControl {
+    None,
+    /// Spins and scales with the keyboard.
+    Spin,
+    /// As `Spin`, and the arrow keys move it too.
+    SpinAndMove,
+}
+
+struct DemoNode {
+    id: NodeId,
+    kind: Option<ShapeKind>,
+    tracks: Vec<Track>,
+    keys: KeyControl,
+    base: Transform,
+    visible: bool,
+}
+
+/// A scene loaded from a `SceneFile`, with each node's animation tracks
+/// played on the scene clock on top of its transform from the file.
+/// "cube 1" and "cube 2" also spin and scale with the keyboard.
+pub struct DemoScene {
+    pub file: SceneFile,
+    pub scene: Scene,
+    nodes: Vec<DemoNode>,
+}
+
+impl DemoScene {
+    /// Uploads the meshes, so needs a current context.
+    pub fn new(file: SceneFile) -> Self {
+        let scene = file.build();
+        let nodes = file
+            .flatten()
+            .into_iter()
+            .enumerate()
+            .map(|(id, desc)| DemoNode {
+                id,
+                kind: desc.shape.map(ShapeKind::of),
+                tracks: desc.animations.clone(),
+                keys: match desc.name.as_str() {
+                    "cube 1" => KeyControl::SpinAndMove,
+                    "cube 2" => KeyControl::Spin,
+                    _ => KeyControl::None,
+                },
+                base: *scene.node(id).transform(),
+                visible: desc.visible,
+            })
+            .collect();
+        Self { file, scene, nodes }
+    }
+
+    /// Poses the nodes for `state` and refreshes the world matrices.
+    pub fn update(&mut self, state: &SceneState) {
+        let spin = Quat::from_euler(EulerRot::XYZ, -state.angle.x, state.angle.y, state.angle.z);
+        for node in &self.nodes {
+            if !node.tracks.is_empty() || node.keys != KeyControl::None {
+                let mut posed = animation::animate(&node.tracks, state.time, &node.base);
+                if node.keys != KeyControl::None {
+                    if node.keys == KeyControl::SpinAndMove {
+                        posed.translation += state.trans;
+                    }
+                    posed.scale *= state.scale;
+                    posed.rotation *= spin;
+                }
+                *self.scene.transform_mut(node.id) = posed;
+            }
+            // Groups without a shape of their own stay, or --shape would
+            // hide everything under them.
+            let shown = node.kind.is_none_or(|kind| state.shows(kind));
+            self.scene.node_mut(node.id).visible = node.visible && shown;
+        }
+        self.scene.update();
+    }
+}
+
+/// A linked program and the uniforms `Renderer` sets on it.
+struct Program {
+    id: GLuint,
+    colourmode_loc: GLint,
+    show_back_faces_loc: GLint,
+    attenuation_loc: GLint,
+    view_inverse_loc: GLint,
+}
+
+impl Program {
+    fn new(id: GLuint) -> Self {
+        unsafe {
+            Self {
+                id,
+                colourmode_loc: gl::GetUniformLocation(id, c"colourmode".as_ptr() as *const _),
+                show_back_faces_loc: gl::GetUniformLocation(id, c"showbackfaces".as_ptr() as *const _),
+                attenuation_loc: gl::GetUniformLocation(id, c"attenuationmode".as_ptr() as *const _),
+                view_inverse_loc: gl::GetUniformLocation(id, c"view_inverse".as_ptr() as *const _),
+            }
+        }
+    }
+}
+
+pub struct Renderer {
+    unlit: Program,
+    /// One per `ShadingModel::ALL`, each looping over the lights in
+    /// `lights`.
+    lit: Vec<Program>,
+    lights: LightBuffer,
+    /// `None` if the shadow map framebuffer couldn't be made.
+    pub shadows: Option<ShadowMaps>,
+}
+
+impl Renderer {
+    /// Compiles the shaders; needs a current context.
+    pub fn new(shadow_settings: ShadowSettings) -> Self {
+        let unlit = Program::new(shader::create_program());
+        let lit = ShadingModel::ALL
+            .iter()
+            .map(|model| {
+                let (vertex, fragment) = model.shaders();
+                Program::new(shader::build_program(vertex, fragment, &format!("{model} program")))
+            })
+            .collect();
+        let shadows = ShadowMaps::new(shadow_settings).map_err(|e| println!("Shadows disabled: {e}")).ok();
+        shader::set_current_program(unlit.id);
+        Self {
+            unlit,
+            lit,
+            lights: LightBuffer::new(),
+            shadows,
+        }
+    }
+
+    /// Draws one frame of `scene` into whatever framebuffer is bound,
+    /// rendering its screens and the shadow maps first.
+    pub fn draw(&mut self, scene: &Scene, state: &SceneState, cam: &Camera, width: u16, height: u16) {
+        let shadowed = if scene.screens().is_empty() {
+            self.render_shadows(scene, state)
+        } else {
+            self.draw_screens(scene, state)
+        };
+        self.draw_view(scene, state, cam, width as f32 / height as f32, true, shadowed);
+
+        if let (Some(shadows), Some(layer)) = (&self.shadows, state.shadow_map_view)
+            && shadowed
+        {
+            shadows.draw_debug(layer, width, height);
+        }
+    }
+
+    /// Renders the shadow maps for `scene` if shadows are on. Returns
+    /// whether it is shadowed.
+    fn render_shadows(&mut self, scene: &Scene, state: &SceneState) -> bool {
+        match &mut self.shadows {
+            Some(shadows) if state.lighting && state.shadows => {
+                shadows.render(scene);
+                shadows.layers() + shadows.cubes() > 0
+            }
+            _ => false,
+        }
+    }
+
+    /// Draws each screen's scene into its framebuffer from its camera,
+    /// leaving the framebuffer and viewport as they were. Screens seen on
+    /// screens are blank. The shadow maps are rendered once per scene, and
+    /// `scene`'s last so the main view can use them; returns whether it is
+    /// shadowed.
+    fn draw_screens(&mut self, scene: &Scene, state: &SceneState) -> bool {
+        let mut framebuffer: GLint = 0;
+        let mut viewport = [0; 4];
+        unsafe {
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+        }
+        for screen in scene.screens() {
+            if let Some(shown) = screen.scene.as_deref() {
+                let shadowed = self.render_shadows(shown, state);
+                self.draw_screen(screen, shown, state, shadowed);
+            }
+        }
+        let shadowed = self.render_shadows(scene, state);
+        for screen in scene.screens().iter().filter(|screen| screen.scene.is_none()) {
+            self.draw_screen(screen, scene, state, shadowed);
+        }
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer as GLuint);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+        }
+        shadowed
+    }
+
+    fn draw_screen(&mut self, screen: &Screen, shown: &Scene, state: &SceneState, shadowed: bool) {
+        let (width, height) = (screen.framebuffer.width(), screen.framebuffer.height());
+        screen.framebuffer.bind();
+        unsafe {
+            gl::Viewport(0, 0, width as i32, height as i32);
+        }
+        self.draw_view(shown, state, &screen.camera, width as f32 / height as f32, false, shadowed);
+    }
+
+    /// Clears the bound framebuffer and draws `scene` from `cam`, with its
+    /// screens blank unless `screens`. `shadowed` says whether the shadow
+    /// maps hold `scene`'s shadows, rendered by `render_shadows`.
+    fn draw_view(&mut self, scene: &Scene, state: &SceneState, cam: &Camera, aspect: f32, screens: bool, shadowed: bool) {
+        let program = if state.lighting {
+            self.lights.upload(&scene.lights, cam.view(), shadowed);
+            let i = ShadingModel::ALL.iter().position(|&model| model == state.shading).unwrap();
+            &self.lit[i]
+        } else {
+            &self.unlit
+        };
+        unsafe {
+            gl::ClearColor(0.0, 0.0, 0.0, 1.0);
+            gl::Clear(gl::COLOR_BUFFER_BIT | gl::DEPTH_BUFFER_BIT);
+            gl::UseProgram(program.id);
+            shader::set_current_program(program.id);
+
+            if program.colourmode_loc != -1 {
+                gl::Uniform1ui(program.colourmode_loc, state.colourmode);
+            }
+            if program.show_back_faces_loc != -1 {
+                gl::Uniform1i(program.show_back_faces_loc, state.show_back_faces as i32);
+            }
+            if program.attenuation_loc != -1 {
+                gl::Uniform1ui(program.attenuation_loc, state.attenuation as u32);
+            }
+            if program.view_inverse_loc != -1 {
+                gl::UniformMatrix4fv(program.view_inverse_loc, 1, gl::FALSE, cam.view().inverse().to_cols_array().as_ptr());
+            }
+        }
+        if let Some(shadows) = &self.shadows
+            && state.lighting
+        {
+            // Bound even when unused, so the sampler always has a texture
+            // of its type.
+            shadows.bind(cam.view());
+        }
+        scene.draw(cam, aspect, screens);
+    }
+}
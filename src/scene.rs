@@ -0,0 +1,470 @@
+use glam::{EulerRot, Mat4, Quat, Vec3};
+
+use crate::camera::Camera;
+use crate::framebuffer::Framebuffer;
+use crate::light::Light;
+use crate::material::{Material, DIFFUSE_UNIT, NORMAL_UNIT};
+use crate::texture::Texture;
+use crate::shader;
+use crate::shape::Shape;
+use crate::skybox::{Skybox, ENVIRONMENT_UNIT};
+
+pub type NodeId = usize;
+pub type MeshId = usize;
+pub type MaterialId = usize;
+pub type TextureId = usize;
+pub type ScreenId = usize;
+
+/// The uploaded textures for a material's maps and its screen, where it
+/// has them and they loaded.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaterialMaps {
+    pub diffuse: Option<TextureId>,
+    pub normal: Option<TextureId>,
+    /// Shown instead of the diffuse map.
+    pub screen: Option<ScreenId>,
+}
+
+/// A camera whose view of a scene is drawn into a texture each frame, for
+/// materials to show in place of a diffuse map.
+pub struct Screen {
+    pub name: String,
+    pub camera: Camera,
+    /// Colour as a texture, depth as a renderbuffer.
+    pub framebuffer: Framebuffer,
+    /// What it shows when that's a scene other than the one it's in,
+    /// posed as it was loaded.
+    pub scene: Option<Box<Scene>>,
+}
+
+/// Local translation, rotation and scale, applied scale first.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self {
+            translation: Vec3::ZERO,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+impl Transform {
+    pub fn from_translation(translation: Vec3) -> Self {
+        Self {
+            translation,
+            ..Self::default()
+        }
+    }
+
+    /// Rotation about x, then y, then z, in radians.
+    pub fn set_euler(&mut self, angles: Vec3) {
+        self.rotation = Quat::from_euler(EulerRot::XYZ, angles.x, angles.y, angles.z);
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        Mat4::from_scale_rotation_translation(self.scale, self.rotation, self.translation)
+    }
+}
+
+pub struct Node {
+    pub name: String,
+    pub mesh: Option<MeshId>,
+    /// Drawn with `Material::default()` if unset.
+    pub material: Option<MaterialId>,
+    /// Hiding a node hides everything under it too.
+    pub visible: bool,
+    /// Whether it's drawn into shadow maps. Unlike `visible` this is only
+    /// about the node itself.
+    pub cast_shadows: bool,
+    transform: Transform,
+    parent: Option<NodeId>,
+    children: Vec<NodeId>,
+    world: Mat4,
+    dirty: bool,
+}
+
+impl Node {
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    pub fn parent(&self) -> Option<NodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &[NodeId] {
+        &self.children
+    }
+
+    /// As of the last `Scene::update`.
+    pub fn world(&self) -> Mat4 {
+        self.world
+    }
+}
+
+/// A tree of named nodes, each with a local transform relative to its
+/// parent and optionally a mesh to draw. World matrices are cached and
+/// only recomputed below nodes whose transform changed. Lights sit in
+/// world space rather than on nodes, and the skybox infinitely far away.
+#[derive(Default)]
+pub struct Scene {
+    nodes: Vec<Node>,
+    meshes: Vec<Box<dyn Shape>>,
+    materials: Vec<(Material, MaterialMaps)>,
+    textures: Vec<Texture>,
+    pub lights: Vec<Light>,
+    /// Drawn behind everything, and mirrored in reflective materials.
+    pub skybox: Option<Skybox>,
+    screens: Vec<Screen>,
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Meshes can be shared between any number of nodes.
+    pub fn add_mesh(&mut self, mesh: Box<dyn Shape>) -> MeshId {
+        self.meshes.push(mesh);
+        self.meshes.len() - 1
+    }
+
+    pub fn add_texture(&mut self, texture: Texture) -> TextureId {
+        self.textures.push(texture);
+        self.textures.len() - 1
+    }
+
+    pub fn add_screen(&mut self, screen: Screen) -> ScreenId {
+        self.screens.push(screen);
+        self.screens.len() - 1
+    }
+
+    pub fn screens(&self) -> &[Screen] {
+        &self.screens
+    }
+
+    /// Like meshes, materials can be shared between nodes.
+    pub fn add_material(&mut self, material: Material, maps: MaterialMaps) -> MaterialId {
+        self.materials.push((material, maps));
+        self.materials.len() - 1
+    }
+
+    pub fn material(&self, id: MaterialId) -> &Material {
+        &self.materials[id].0
+    }
+
+    pub fn material_mut(&mut self, id: MaterialId) -> &mut Material {
+        &mut self.materials[id].0
+    }
+
+    pub fn add_node(&mut self, name: &str, parent: Option<NodeId>, mesh: Option<MeshId>, transform: Transform) -> NodeId {
+        let id = self.nodes.len();
+        self.nodes.push(Node {
+            name: name.to_string(),
+            mesh,
+            material: None,
+            visible: true,
+            cast_shadows: true,
+            transform,
+            parent,
+            children: vec![],
+            world: Mat4::IDENTITY,
+            dirty: true,
+        });
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(id);
+        }
+        id
+    }
+
+    /// Moves `id` under `parent`, or to the top level for `None`. Refuses,
+    /// returning false, if that would make a node its own ancestor.
+    pub fn set_parent(&mut self, id: NodeId, parent: Option<NodeId>) -> bool {
+        let mut ancestor = parent;
+        while let Some(a) = ancestor {
+            if a == id {
+                return false;
+            }
+            ancestor = self.nodes[a].parent;
+        }
+
+        if let Some(old) = self.nodes[id].parent {
+            self.nodes[old].children.retain(|&child| child != id);
+        }
+        if let Some(parent) = parent {
+            self.nodes[parent].children.push(id);
+        }
+        self.nodes[id].parent = parent;
+        self.nodes[id].dirty = true;
+        true
+    }
+
+    pub fn find(&self, name: &str) -> Option<NodeId> {
+        self.nodes.iter().position(|node| node.name == name)
+    }
+
+    pub fn node(&self, id: NodeId) -> &Node {
+        &self.nodes[id]
+    }
+
+    pub fn node_mut(&mut self, id: NodeId) -> &mut Node {
+        &mut self.nodes[id]
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = (NodeId, &Node)> {
+        self.nodes.iter().enumerate()
+    }
+
+    /// Changing the transform through here marks the node for `update`.
+    pub fn transform_mut(&mut self, id: NodeId) -> &mut Transform {
+        let node = &mut self.nodes[id];
+        node.dirty = true;
+        &mut node.transform
+    }
+
+    fn roots(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nodes.iter().enumerate().filter(|(_, node)| node.parent.is_none()).map(|(id, _)| id)
+    }
+
+    /// Recomputes the world matrices of changed nodes and their subtrees.
+    pub fn update(&mut self) {
+        let mut stack: Vec<(NodeId, bool)> = self.roots().map(|id| (id, false)).collect();
+        while let Some((id, parent_changed)) = stack.pop() {
+            let node = &self.nodes[id];
+            let changed = parent_changed || node.dirty;
+            if changed {
+                let parent_world = node.parent.map_or(Mat4::IDENTITY, |p| self.nodes[p].world);
+                let node = &mut self.nodes[id];
+                node.world = parent_world * node.transform.matrix();
+                node.dirty = false;
+            }
+            stack.extend(self.nodes[id].children.iter().map(|&child| (child, changed)));
+        }
+    }
+
+    /// Whether the node and all its ancestors are visible.
+    pub fn is_visible(&self, id: NodeId) -> bool {
+        let mut node = Some(id);
+        while let Some(id) = node {
+            if !self.nodes[id].visible {
+                return false;
+            }
+            node = self.nodes[id].parent;
+        }
+        true
+    }
+
+    /// Sets the camera and environment uniforms on the current program and
+    /// draws every visible node that has a mesh, each with its material,
+    /// then the skybox behind them. Transparent nodes go last, furthest
+    /// first, so they blend over everything behind them. Materials show
+    /// their screens only if `screens` is set, which it mustn't be while
+    /// drawing into one. Call `update` first.
+    pub fn draw(&self, cam: &Camera, aspect: f32, screens: bool) {
+        let view = cam.view();
+        let projection = cam.projection(aspect);
+        unsafe {
+            let program = shader::get_current_program();
+            let view_loc = gl::GetUniformLocation(program, c"view".as_ptr() as *const _);
+            if view_loc != -1 {
+                gl::UniformMatrix4fv(view_loc, 1, gl::FALSE, view.to_cols_array().as_ptr());
+            }
+            let proj_loc = gl::GetUniformLocation(program, c"projection".as_ptr() as *const _);
+            if proj_loc != -1 {
+                gl::UniformMatrix4fv(proj_loc, 1, gl::FALSE, projection.to_cols_array().as_ptr());
+            }
+            let mapped_loc = gl::GetUniformLocation(program, c"environment_mapped".as_ptr() as *const _);
+            if mapped_loc != -1 {
+                gl::Uniform1i(mapped_loc, self.skybox.is_some() as i32);
+            }
+            let sampler_loc = gl::GetUniformLocation(program, c"environment".as_ptr() as *const _);
+            if sampler_loc != -1 {
+                gl::Uniform1i(sampler_loc, ENVIRONMENT_UNIT as i32);
+            }
+        }
+        if let Some(skybox) = &self.skybox {
+            skybox.cube.bind(ENVIRONMENT_UNIT);
+            unsafe {
+                gl::ActiveTexture(gl::TEXTURE0);
+            }
+        }
+
+        let default = (Material::default(), MaterialMaps::default());
+        let (mut transparent, opaque): (Vec<_>, Vec<_>) = self
+            .drawable()
+            .map(|node| (node, node.material.map_or(&default, |id| &self.materials[id])))
+            .partition(|(_, (material, _))| material.is_transparent());
+        // Most negative view-space z is furthest away.
+        let depth = |node: &Node| view.transform_point3(node.world.w_axis.truncate()).z;
+        transparent.sort_by(|(a, _), (b, _)| depth(a).total_cmp(&depth(b)));
+
+        for (node, material) in opaque {
+            self.draw_node(node, material, screens);
+        }
+        if let Some(skybox) = &self.skybox {
+            skybox.draw(view, projection);
+        }
+        if transparent.is_empty() {
+            return;
+        }
+        unsafe {
+            gl::Enable(gl::BLEND);
+            gl::BlendFuncSeparate(gl::SRC_ALPHA, gl::ONE_MINUS_SRC_ALPHA, gl::ONE, gl::ONE_MINUS_SRC_ALPHA);
+            gl::DepthMask(gl::FALSE);
+        }
+        for (node, material) in transparent {
+            self.draw_node(node, material, screens);
+        }
+        unsafe {
+            gl::DepthMask(gl::TRUE);
+            gl::Disable(gl::BLEND);
+        }
+    }
+
+    /// Draws the opaque visible nodes that cast shadows, with no
+    /// materials, for a depth-only pass such as a shadow map's, through
+    /// the current program.
+    pub fn draw_shadow_casters(&self) {
+        for node in self.drawable() {
+            if node.cast_shadows && !node.material.is_some_and(|id| self.materials[id].0.is_transparent()) {
+                self.meshes[node.mesh.unwrap()].draw(node.world);
+            }
+        }
+    }
+
+    /// A sphere around everything `draw` would draw, as a centre and
+    /// radius, or `None` if that's nothing. Call `update` first.
+    pub fn bounds(&self) -> Option<(Vec3, f32)> {
+        let spheres: Vec<(Vec3, f32)> = self
+            .drawable()
+            .map(|node| {
+                let scale = node.world.x_axis.length().max(node.world.y_axis.length()).max(node.world.z_axis.length());
+                (node.world.w_axis.truncate(), self.meshes[node.mesh.unwrap()].radius() * scale)
+            })
+            .collect();
+        let min = spheres.iter().map(|&(c, r)| c - r).reduce(Vec3::min)?;
+        let max = spheres.iter().map(|&(c, r)| c + r).reduce(Vec3::max)?;
+        let centre = (min + max) / 2.0;
+        let radius = spheres.iter().map(|&(c, r)| c.distance(centre) + r).fold(0.0, f32::max);
+        Some((centre, radius))
+    }
+
+    fn draw_node(&self, node: &Node, (material, maps): &(Material, MaterialMaps), screens: bool) {
+        let screen = maps.screen.filter(|_| screens);
+        if let Some(screen) = screen {
+            self.screens[screen].framebuffer.bind_colour(DIFFUSE_UNIT);
+        } else if let Some(texture) = maps.diffuse {
+            self.textures[texture].bind(DIFFUSE_UNIT);
+        }
+        if let Some(texture) = maps.normal {
+            self.textures[texture].bind(NORMAL_UNIT);
+        }
+        material.upload(screen.is_some() || maps.diffuse.is_some(), maps.normal.is_some());
+        self.meshes[node.mesh.unwrap()].draw(node.world);
+    }
+
+    /// The visible nodes with a mesh, parents first.
+    fn drawable(&self) -> impl Iterator<Item = &Node> {
+        let mut stack: Vec<NodeId> = self.roots().collect();
+        std::iter::from_fn(move || {
+            while let Some(id) = stack.pop() {
+                let node = &self.nodes[id];
+                if node.visible {
+                    stack.extend(&node.children);
+                    if node.mesh.is_some() {
+                        return Some(node);
+                    }
+                }
+            }
+            None
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_near(a: Mat4, b: Mat4) {
+        assert!(a.abs_diff_eq(b, 1e-5), "{a} != {b}");
+    }
+
+    #[test]
+    fn world_matrices_compose_down_the_tree() {
+        let mut scene = Scene::new();
+        let base = scene.add_node("base", None, None, Transform::from_translation(Vec3::X));
+        let arm = scene.add_node("arm", Some(base), None, Transform::from_translation(Vec3::Y));
+        let hand = scene.add_node("hand", Some(arm), None, Transform::from_translation(Vec3::Z));
+        scene.update();
+        assert_near(scene.node(hand).world(), Mat4::from_translation(Vec3::ONE));
+
+        // Turning the base swings everything attached to it.
+        scene.transform_mut(base).rotation = Quat::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        scene.update();
+        let turned_base = Mat4::from_translation(Vec3::X) * Mat4::from_rotation_z(std::f32::consts::FRAC_PI_2);
+        assert_near(scene.node(arm).world(), turned_base * Mat4::from_translation(Vec3::Y));
+        let tip = scene.node(hand).world().transform_point3(Vec3::ZERO);
+        assert!(tip.abs_diff_eq(Vec3::new(0.0, 0.0, 1.0), 1e-5), "{tip}");
+    }
+
+    #[test]
+    fn reparenting_moves_the_subtree() {
+        let mut scene = Scene::new();
+        let a = scene.add_node("a", None, None, Transform::from_translation(Vec3::X));
+        let b = scene.add_node("b", None, None, Transform::from_translation(Vec3::Y));
+        let child = scene.add_node("child", Some(a), None, Transform::default());
+        assert!(scene.set_parent(child, Some(b)));
+        scene.update();
+        assert_eq!(scene.node(a).children(), &[] as &[NodeId]);
+        assert_near(scene.node(child).world(), Mat4::from_translation(Vec3::Y));
+        assert!(!scene.set_parent(b, Some(child)), "made a cycle");
+    }
+
+    /// Stands in for a mesh without needing a context.
+    struct Ball(f32);
+
+    impl Shape for Ball {
+        fn draw(&self, _: Mat4) {}
+
+        fn radius(&self) -> f32 {
+            self.0
+        }
+    }
+
+    #[test]
+    fn bounds_cover_the_visible_meshes() {
+        let mut scene = Scene::new();
+        assert_eq!(scene.bounds(), None);
+        let ball = scene.add_mesh(Box::new(Ball(1.0)));
+        scene.add_node("left", None, Some(ball), Transform::from_translation(Vec3::new(-2.0, 0.0, 0.0)));
+        let mut big = Transform::from_translation(Vec3::new(3.0, 0.0, 0.0));
+        big.scale = Vec3::new(1.0, 2.0, 1.0);
+        scene.add_node("right", None, Some(ball), big);
+        let hidden = scene.add_node("hidden", None, Some(ball), Transform::from_translation(Vec3::splat(50.0)));
+        scene.node_mut(hidden).visible = false;
+        scene.update();
+
+        let (centre, radius) = scene.bounds().unwrap();
+        // -3 to 5 along x, and the scaled ball reaches 2 out from (3, 0, 0).
+        assert!(centre.abs_diff_eq(Vec3::new(1.0, 0.0, 0.0), 1e-5), "{centre}");
+        assert!((radius - 4.0).abs() < 1e-5, "{radius}");
+    }
+
+    #[test]
+    fn hidden_parents_hide_children() {
+        let mut scene = Scene::new();
+        let parent = scene.add_node("parent", None, None, Transform::default());
+        let child = scene.add_node("child", Some(parent), None, Transform::default());
+        assert!(scene.is_visible(child));
+        scene.node_mut(parent).visible = false;
+        assert!(!scene.is_visible(child));
+        assert_eq!(scene.find("child"), Some(child));
+    }
+}
@@ -0,0 +1,592 @@
+//! Human-editable scene descriptions in RON. A file lists the camera,
+//! named materials, lights, a skybox, screens and a tree of nodes:
+//!
+//! ```ron
+//! (
+//!     camera: (position: (0.0, 0.0, 2.0), yaw: -90.0, pitch: 0.0),
+//!     materials: {
+//!         "teal": (diffuse: (0.2, 0.6, 0.8), shininess: 32.0),
+//!         "crate": (diffuse_map: Some((path: "textures/crate.png", filter: Nearest))),
+//!         "monitor": (screen: Some("overhead")),
+//!     },
+//!     material_libraries: ["metals.mtl"],
+//!     lights: [(kind: Point, position: (1.0, 2.0, 1.0), range: 10.0)],
+//!     skybox: Some(Equirectangular("textures/sky.png")),
+//!     screens: {
+//!         "overhead": (camera: (position: (0.0, 4.0, 0.1), yaw: -90.0, pitch: -85.0), size: (256, 256)),
+//!     },
+//!     nodes: [
+//!         (name: "base", shape: Some(Cylinder(segments: 32, radius: 0.2, height: 0.1)), children: [
+//!             (name: "arm", shape: Some(Cube), translation: (0.0, 0.3, 0.0), rotation: (0.0, 0.0, 30.0)),
+//!         ]),
+//!     ],
+//! )
+//! ```
+//!
+//! Everything but `nodes` can be left out, as can any node field other
+//! than `name`. Rotations are XYZ Euler angles in degrees. Texture,
+//! skybox, screen and library paths are relative to the scene file.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+use glam::{Vec3, Vec4};
+use serde::{Deserialize, Serialize};
+
+use crate::animation::{Property, Track};
+use crate::camera::Camera;
+use crate::light::{Light, MAX_LIGHTS};
+use crate::material::{self, Material};
+use crate::geometry::{self, MeshData, CYLINDER_COLOUR, SPHERE_COLOUR};
+use crate::framebuffer::{Framebuffer, FramebufferDesc, Storage};
+use crate::scene::{MaterialId, MaterialMaps, MeshId, NodeId, Scene, Screen, ScreenId, TextureId, Transform};
+use crate::shape::Mesh;
+use crate::skybox::{Skybox, SkyboxDesc};
+use crate::texture::{Texture, TextureMap};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SceneFile {
+    #[serde(default)]
+    pub camera: CameraDesc,
+    #[serde(default)]
+    pub materials: BTreeMap<String, Material>,
+    /// `.mtl` files, relative to the scene file, whose materials nodes can
+    /// use too. Materials in `materials` win over ones loaded from here.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub material_libraries: Vec<PathBuf>,
+    /// What was loaded from `material_libraries`, with texture paths
+    /// made relative to the scene file like everything else.
+    #[serde(skip)]
+    pub library: BTreeMap<String, Material>,
+    /// Where the paths in the file start from: the directory it was
+    /// loaded from.
+    #[serde(skip)]
+    pub dir: PathBuf,
+    #[serde(default)]
+    pub lights: Vec<Light>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub skybox: Option<SkyboxDesc>,
+    /// Views drawn into textures, by the name materials show them by.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub screens: BTreeMap<String, ScreenDesc>,
+    pub nodes: Vec<NodeDesc>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CameraDesc {
+    pub position: Vec3,
+    /// Degrees; -90 looks down -z.
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ScreenDesc {
+    pub camera: CameraDesc,
+    /// Width and height of the picture in pixels.
+    #[serde(default = "screen_size")]
+    pub size: (u16, u16),
+    /// A scene file to show instead of this one, relative to it, posed as
+    /// the file has it: its animations don't play and its own screens
+    /// stay blank.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub scene: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum ShapeDesc {
+    Cube,
+    Pyramid,
+    Sphere { lats: usize, longs: usize },
+    Cylinder { segments: usize, radius: f32, height: f32 },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct NodeDesc {
+    pub name: String,
+    #[serde(default)]
+    pub shape: Option<ShapeDesc>,
+    /// Key into `SceneFile::materials`.
+    #[serde(default)]
+    pub material: Option<String>,
+    #[serde(default)]
+    pub translation: Vec3,
+    #[serde(default)]
+    pub rotation: Vec3,
+    #[serde(default = "one")]
+    pub scale: Vec3,
+    #[serde(default = "yes")]
+    pub visible: bool,
+    /// Off for things that shouldn't block light, like a lamp's bulb.
+    #[serde(default = "yes")]
+    pub cast_shadows: bool,
+    /// Played in order on top of the transform above.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub animations: Vec<Track>,
+    #[serde(default)]
+    pub children: Vec<NodeDesc>,
+}
+
+fn one() -> Vec3 {
+    Vec3::ONE
+}
+
+fn yes() -> bool {
+    true
+}
+
+fn screen_size() -> (u16, u16) {
+    (256, 256)
+}
+
+/// Largest screen width or height, well within any driver's limit.
+const MAX_SCREEN_SIZE: u16 = 4096;
+
+impl Default for CameraDesc {
+    fn default() -> Self {
+        Self::from_camera(&Camera::default())
+    }
+}
+
+impl CameraDesc {
+    pub fn from_camera(cam: &Camera) -> Self {
+        Self {
+            position: cam.position(),
+            yaw: cam.yaw.to_degrees(),
+            pitch: cam.pitch.to_degrees(),
+        }
+    }
+
+    pub fn to_camera(self) -> Camera {
+        Camera {
+            x: self.position.x,
+            y: self.position.y,
+            z: self.position.z,
+            yaw: self.yaw.to_radians(),
+            pitch: self.pitch.to_radians(),
+        }
+    }
+}
+
+impl ShapeDesc {
+    pub fn generate(self) -> MeshData {
+        match self {
+            ShapeDesc::Cube => geometry::cube(),
+            ShapeDesc::Pyramid => geometry::pyramid(),
+            ShapeDesc::Sphere { lats, longs } => geometry::sphere(lats.max(2), longs.max(3), SPHERE_COLOUR),
+            ShapeDesc::Cylinder { segments, radius, height } => {
+                geometry::cylinder(segments.max(3), radius, height, CYLINDER_COLOUR)
+            }
+        }
+    }
+
+    fn label(self) -> String {
+        match self {
+            ShapeDesc::Cube => "Cube".into(),
+            ShapeDesc::Pyramid => "Pyramid".into(),
+            ShapeDesc::Sphere { lats, longs } => format!("Sphere {lats}x{longs}"),
+            ShapeDesc::Cylinder { segments, .. } => format!("Cylinder {segments}"),
+        }
+    }
+}
+
+impl NodeDesc {
+    fn new(name: &str, shape: ShapeDesc, translation: Vec3) -> Self {
+        Self {
+            name: name.into(),
+            shape: Some(shape),
+            material: None,
+            translation,
+            rotation: Vec3::ZERO,
+            scale: Vec3::ONE,
+            visible: true,
+            cast_shadows: true,
+            animations: vec![],
+            children: vec![],
+        }
+    }
+
+    fn transform(&self) -> Transform {
+        let mut transform = Transform {
+            translation: self.translation,
+            scale: self.scale,
+            ..Transform::default()
+        };
+        let radians = self.rotation * std::f32::consts::PI / 180.0;
+        transform.set_euler(radians);
+        transform
+    }
+}
+
+/// The demo scene the app shows without `--scene`.
+impl Default for SceneFile {
+    fn default() -> Self {
+        let mut sphere = NodeDesc::new("sphere", ShapeDesc::Sphere { lats: 80, longs: 80 }, Vec3::new(0.0, 0.0, -2.0));
+        sphere.scale = Vec3::splat(0.7);
+        // Bobs once every 2 pi seconds while turning a radian a second.
+        let mut pyramid = NodeDesc::new("pyramid", ShapeDesc::Pyramid, Vec3::new(0.0, 0.0, -1.0));
+        pyramid.animations = vec![
+            Track::Oscillate {
+                property: Property::Translation,
+                axis: Vec3::Y,
+                amplitude: 0.5,
+                period: std::f32::consts::TAU,
+                phase: 0.0,
+            },
+            Track::Spin {
+                axis: Vec3::Y,
+                speed: 1f32.to_degrees(),
+            },
+        ];
+        Self {
+            camera: CameraDesc::default(),
+            materials: BTreeMap::new(),
+            material_libraries: vec![],
+            library: BTreeMap::new(),
+            dir: PathBuf::new(),
+            lights: vec![],
+            skybox: None,
+            screens: BTreeMap::new(),
+            nodes: vec![
+                NodeDesc::new("cube 1", ShapeDesc::Cube, Vec3::new(-0.5, 0.0, 0.0)),
+                NodeDesc::new("cube 2", ShapeDesc::Cube, Vec3::new(0.5, 0.0, 0.0)),
+                pyramid,
+                sphere,
+            ],
+        }
+    }
+}
+
+impl SceneFile {
+    /// Material libraries and textures are looked for relative to the
+    /// working directory; `load` looks next to the file instead.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        Self::parse_in(source, Path::new(""))
+    }
+
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| format!("{}: {e}", path.display()))?;
+        let dir = path.parent().unwrap_or(Path::new(""));
+        Self::parse_in(&source, dir).map_err(|e| format!("{}: {e}", path.display()))
+    }
+
+    fn parse_in(source: &str, dir: &Path) -> Result<Self, String> {
+        let mut file: SceneFile = ron::from_str(source).map_err(|e| e.to_string())?;
+        file.dir = dir.to_path_buf();
+        for library in &file.material_libraries {
+            let library_dir = library.parent().unwrap_or(Path::new(""));
+            for (name, mut material) in material::load_mtl(&dir.join(library))? {
+                for map in [&mut material.diffuse_map, &mut material.normal_map].into_iter().flatten() {
+                    map.path = library_dir.join(&map.path);
+                }
+                file.library.insert(name, material);
+            }
+        }
+        file.check()?;
+        Ok(file)
+    }
+
+    /// Looks in `materials`, then the libraries.
+    pub fn material(&self, name: &str) -> Option<&Material> {
+        self.materials.get(name).or_else(|| self.library.get(name))
+    }
+
+    pub fn to_ron(&self) -> String {
+        let config = ron::ser::PrettyConfig::new().depth_limit(6).struct_names(false);
+        ron::ser::to_string_pretty(self, config).expect("scene descriptions always serialize")
+    }
+
+    pub fn save(&self, path: &Path) -> std::io::Result<()> {
+        std::fs::write(path, self.to_ron())
+    }
+
+    /// Catches what the parser can't: unknown materials and screens,
+    /// malformed animation tracks, impossible lights, skyboxes without six
+    /// faces and screens without pixels.
+    fn check(&self) -> Result<(), String> {
+        if self.lights.len() > MAX_LIGHTS {
+            return Err(format!("{} lights, but the shaders take at most {MAX_LIGHTS}", self.lights.len()));
+        }
+        for (i, light) in self.lights.iter().enumerate() {
+            light.check().map_err(|e| format!("light {i}: {e}"))?;
+        }
+        for (name, material) in &self.materials {
+            material.check().map_err(|e| format!("material '{name}': {e}"))?;
+        }
+        if let Some(skybox) = &self.skybox {
+            skybox.check()?;
+        }
+        for (name, screen) in &self.screens {
+            let (width, height) = screen.size;
+            if !(1..=MAX_SCREEN_SIZE).contains(&width) || !(1..=MAX_SCREEN_SIZE).contains(&height) {
+                return Err(format!("screen '{name}' is {width}x{height}, outside 1 to {MAX_SCREEN_SIZE}"));
+            }
+        }
+        for (name, material) in self.materials.iter().chain(&self.library) {
+            if let Some(screen) = &material.screen
+                && !self.screens.contains_key(screen)
+            {
+                return Err(format!("material '{name}' shows undefined screen '{screen}'"));
+            }
+        }
+        let mut stack: Vec<&NodeDesc> = self.nodes.iter().collect();
+        while let Some(node) = stack.pop() {
+            for track in &node.animations {
+                track.check().map_err(|e| format!("node '{}': {e}", node.name))?;
+            }
+            if let Some(material) = &node.material
+                && self.material(material).is_none()
+            {
+                return Err(format!("node '{}' uses undefined material '{material}'", node.name));
+            }
+            stack.extend(&node.children);
+        }
+        Ok(())
+    }
+
+    /// Every node, parents before children, in the order `build` adds
+    /// them: the node at index `i` becomes `NodeId` `i`.
+    pub fn flatten(&self) -> Vec<&NodeDesc> {
+        let mut nodes = vec![];
+        let mut stack: Vec<&NodeDesc> = self.nodes.iter().rev().collect();
+        while let Some(node) = stack.pop() {
+            nodes.push(node);
+            stack.extend(node.children.iter().rev());
+        }
+        nodes
+    }
+
+    /// Generates and uploads the meshes and builds the node tree. Nodes
+    /// with the same shape share a mesh, and nodes with the same material
+    /// share it. Needs a current context.
+    pub fn build(&self) -> Scene {
+        let mut builder = Builder {
+            scene: Scene::new(),
+            meshes: HashMap::new(),
+            materials: HashMap::new(),
+            textures: HashMap::new(),
+            screens: HashMap::new(),
+        };
+        builder.scene.lights = self.lights.clone();
+        if let Some(skybox) = &self.skybox {
+            builder.scene.skybox =
+                Skybox::load(skybox, &self.dir).map_err(|e| println!("Scene drawn without its skybox: {e}")).ok();
+        }
+        for (name, desc) in &self.screens {
+            match self.build_screen(name, desc) {
+                Ok(screen) => {
+                    let id = builder.scene.add_screen(screen);
+                    builder.screens.insert(name.clone(), id);
+                }
+                Err(e) => println!("Screen '{name}' left blank: {e}"),
+            }
+        }
+        for node in &self.nodes {
+            self.add_node(&mut builder, node, None);
+        }
+        builder.scene.update();
+        builder.scene
+    }
+
+    /// The screen's framebuffer, and the scene it shows if that's another
+    /// file, built without screens of its own so files can't show each
+    /// other forever.
+    fn build_screen(&self, name: &str, desc: &ScreenDesc) -> Result<Screen, String> {
+        let scene = match &desc.scene {
+            Some(path) => {
+                let mut file = SceneFile::load(&self.dir.join(path))?;
+                file.screens.clear();
+                Some(Box::new(file.build()))
+            }
+            None => None,
+        };
+        let framebuffer_desc = FramebufferDesc {
+            colour: Some(Storage::Texture),
+            depth: Some(Storage::Renderbuffer),
+            samples: 0,
+        };
+        let (width, height) = desc.size;
+        Ok(Screen {
+            name: name.to_string(),
+            camera: desc.camera.to_camera(),
+            framebuffer: Framebuffer::new(width, height, framebuffer_desc, &format!("Screen '{name}'"))?,
+            scene,
+        })
+    }
+
+    fn add_material(&self, builder: &mut Builder, name: &str) -> MaterialId {
+        if let Some(&id) = builder.materials.get(name) {
+            return id;
+        }
+        let material = self.material(name).expect("checked when loaded").clone();
+        let mut texture = |map: &Option<TextureMap>| {
+            let map = map.as_ref()?;
+            *builder.textures.entry(map.clone()).or_insert_with(|| {
+                let resolved = TextureMap {
+                    path: self.dir.join(&map.path),
+                    ..map.clone()
+                };
+                Texture::load(&resolved)
+                    .map(|texture| builder.scene.add_texture(texture))
+                    .map_err(|e| println!("Material '{name}' drawn without a texture: {e}"))
+                    .ok()
+            })
+        };
+        let maps = MaterialMaps {
+            diffuse: texture(&material.diffuse_map),
+            normal: texture(&material.normal_map),
+            screen: material.screen.as_ref().and_then(|screen| builder.screens.get(screen).copied()),
+        };
+        let id = builder.scene.add_material(material, maps);
+        builder.materials.insert(name.to_string(), id);
+        id
+    }
+
+    fn add_node(&self, builder: &mut Builder, desc: &NodeDesc, parent: Option<NodeId>) {
+        let material = desc.material.as_ref().map(|name| self.add_material(builder, name));
+        let scene = &mut builder.scene;
+        // Materials tint the vertex colours, so shapes that have one get
+        // theirs whitened to show the material's own colour.
+        let tinted = desc.material.is_some();
+        let mesh = desc.shape.map(|shape| {
+            *builder.meshes.entry(format!("{shape:?} {tinted}")).or_insert_with(|| {
+                let mut data = shape.generate();
+                let mut label = shape.label();
+                if tinted {
+                    data.colours.fill(Vec4::ONE);
+                    label = format!("{label} (white)");
+                }
+                scene.add_mesh(Box::new(Mesh::new(data, &label)))
+            })
+        });
+
+        let id = scene.add_node(&desc.name, parent, mesh, desc.transform());
+        let node = scene.node_mut(id);
+        node.visible = desc.visible;
+        node.cast_shadows = desc.cast_shadows;
+        node.material = material;
+        for child in &desc.children {
+            self.add_node(builder, child, Some(id));
+        }
+    }
+}
+
+/// What `SceneFile::build` has made so far, so it can share it.
+struct Builder {
+    scene: Scene,
+    meshes: HashMap<String, MeshId>,
+    materials: HashMap<String, MaterialId>,
+    /// `None` for textures that failed to load, so they're only tried once.
+    textures: HashMap<TextureMap, Option<TextureId>>,
+    /// Screens that were made, by name.
+    screens: HashMap<String, ScreenId>,
+}
+
+/// How often `SceneWatcher` checks the file's modification time.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Polls a scene file for changes, for live reloading.
+pub struct SceneWatcher {
+    pub path: PathBuf,
+    modified: Option<SystemTime>,
+    last_poll: Instant,
+}
+
+impl SceneWatcher {
+    pub fn new(path: &Path) -> Self {
+        Self {
+            path: path.to_path_buf(),
+            modified: modified(path),
+            last_poll: Instant::now(),
+        }
+    }
+
+    /// The reparsed file if it changed since the last poll. A file that
+    /// fails to parse is reported once and skipped until it changes again.
+    pub fn poll(&mut self) -> Option<Result<SceneFile, String>> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return None;
+        }
+        self.last_poll = Instant::now();
+        let modified = modified(&self.path);
+        if modified.is_none() || modified == self.modified {
+            return None;
+        }
+        self.modified = modified;
+        Some(SceneFile::load(&self.path))
+    }
+
+    /// Writes `file` without the write being picked up as an edit.
+    pub fn save(&mut self, file: &SceneFile) -> std::io::Result<()> {
+        file.save(&self.path)?;
+        self.modified = modified(&self.path);
+        Ok(())
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_scene_round_trips() {
+        let file = SceneFile::default();
+        assert_eq!(SceneFile::parse(&file.to_ron()).unwrap(), file);
+    }
+
+    #[test]
+    fn omitted_fields_take_defaults() {
+        let file = SceneFile::parse(r#"(nodes: [(name: "empty", children: [(name: "ball", shape: Some(Sphere(lats: 8, longs: 8)))])])"#).unwrap();
+        assert_eq!(file.camera, CameraDesc::default());
+        let node = &file.nodes[0];
+        assert_eq!((node.shape, node.scale, node.visible), (None, Vec3::ONE, true));
+        assert_eq!(node.children[0].shape, Some(ShapeDesc::Sphere { lats: 8, longs: 8 }));
+    }
+
+    #[test]
+    fn undefined_materials_are_rejected() {
+        let err = SceneFile::parse(r#"(nodes: [(name: "a", shape: Some(Cube), material: Some("gold"))])"#).unwrap_err();
+        assert!(err.contains("gold"), "{err}");
+    }
+
+    #[test]
+    fn bad_animations_name_their_node() {
+        let err = SceneFile::parse(r#"(nodes: [(name: "wobbly", animations: [Scale(keys: [])])])"#).unwrap_err();
+        assert!(err.contains("wobbly"), "{err}");
+    }
+
+    #[test]
+    fn skyboxes_need_six_faces() {
+        let err = SceneFile::parse(r#"(skybox: Some(Faces(["a.png", "b.png"])), nodes: [])"#).unwrap_err();
+        assert!(err.contains("6 faces"), "{err}");
+        let file = SceneFile::parse(r#"(skybox: Some(Equirectangular("sky.png")), nodes: [])"#).unwrap();
+        assert_eq!(file.skybox, Some(SkyboxDesc::Equirectangular("sky.png".into())));
+    }
+
+    #[test]
+    fn screens_must_exist_and_have_pixels() {
+        let err = SceneFile::parse(r#"(materials: {"tv": (screen: Some("news"))}, nodes: [])"#).unwrap_err();
+        assert!(err.contains("'tv'") && err.contains("'news'"), "{err}");
+        let err = SceneFile::parse(r#"(screens: {"news": (camera: (position: (0.0, 0.0, 0.0), yaw: -90.0, pitch: 0.0), size: (0, 64))}, nodes: [])"#).unwrap_err();
+        assert!(err.contains("0x64"), "{err}");
+        let file = SceneFile::parse(r#"(screens: {"news": (camera: (position: (0.0, 1.0, 0.0), yaw: -90.0, pitch: 0.0))}, materials: {"tv": (screen: Some("news"))}, nodes: [])"#).unwrap();
+        assert_eq!((file.screens["news"].size, &file.screens["news"].scene), ((256, 256), &None));
+    }
+
+    #[test]
+    fn example_scenes_load() {
+        for entry in std::fs::read_dir("scenes").unwrap() {
+            let path = entry.unwrap().path();
+            if path.extension().is_some_and(|ext| ext == "ron") {
+                SceneFile::load(&path).unwrap();
+            }
+        }
+        let showroom = SceneFile::load(Path::new("scenes/showroom.ron")).unwrap();
+        assert!(showroom.material("brass").is_some() && showroom.material("floor").is_some());
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use gl::types::GLuint;
+
+use crate::image::Image;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Png,
+    /// Written by hand, so it works even if PNG encoding fails.
+    Ppm,
+}
+
+impl Format {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Png => "png",
+            Format::Ppm => "ppm",
+        }
+    }
+
+    /// PPM for `.ppm`/`.pnm` paths, PNG for anything else.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some(ext) if ext.eq_ignore_ascii_case("ppm") || ext.eq_ignore_ascii_case("pnm") => Format::Ppm,
+            _ => Format::Png,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScreenshotOptions {
+    pub dir: PathBuf,
+    pub format: Format,
+    /// Keep the framebuffer's alpha channel instead of saving it opaque.
+    pub alpha: bool,
+}
+
+impl Default for ScreenshotOptions {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("."),
+            format: Format::Png,
+            alpha: false,
+        }
+    }
+}
+
+/// Reads back framebuffer `fbo` (0 for the window's back buffer). Call it
+/// after the frame is drawn, and resolved if multisampled, but before the
+/// swap, after which the back buffer's contents are undefined.
+pub fn capture(fbo: GLuint, width: u16, height: u16, alpha: bool) -> Image {
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, fbo);
+        if fbo == 0 {
+            gl::ReadBuffer(gl::BACK);
+        }
+    }
+    let mut image = Image::read_framebuffer(width as u32, height as u32);
+    unsafe {
+        gl::BindFramebuffer(gl::READ_FRAMEBUFFER, 0);
+    }
+    if !alpha {
+        image.make_opaque();
+    }
+    image
+}
+
+/// Writes `image` to `path` in `format`. A failed PNG is retried as PPM
+/// next to it, so a screenshot is never lost. Returns the path written.
+pub fn write(image: &Image, path: &Path, format: Format, alpha: bool) -> std::io::Result<PathBuf> {
+    if format == Format::Png {
+        match image.write_png(path, alpha) {
+            Ok(()) => return Ok(path.to_path_buf()),
+            Err(e) => println!("PNG encoding of {} failed ({e}), writing PPM instead", path.display()),
+        }
+    }
+    let path = path.with_extension(Format::Ppm.extension());
+    image.write_ppm(&path)?;
+    Ok(path)
+}
+
+/// Captures the window's back buffer into a timestamped file in `opts.dir`.
+pub fn save(width: u16, height: u16, opts: &ScreenshotOptions) -> std::io::Result<PathBuf> {
+    let image = capture(0, width, height, opts.alpha);
+    std::fs::create_dir_all(&opts.dir)?;
+    let name = format!("screenshot-{}.{}", timestamp(SystemTime::now()), opts.format.extension());
+    write(&image, &opts.dir.join(name), opts.format, opts.alpha)
+}
+
+/// UTC `YYYYMMDD-HHMMSS-mmm`, which sorts chronologically.
+pub fn timestamp(time: SystemTime) -> String {
+    let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+    let secs = since_epoch.as_secs() as i64;
+    let (days, secs_of_day) = (secs.div_euclid(86_400), secs.rem_euclid(86_400));
+
+    // Howard Hinnant's days-to-civil conversion.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!(
+        "{year:04}{month:02}{day:02}-{:02}{:02}{:02}-{:03}",
+        secs_of_day / 3600,
+        secs_of_day / 60 % 60,
+        secs_of_day % 60,
+        since_epoch.subsec_millis(),
+    )
+}
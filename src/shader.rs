@@ -1,6 +1,8 @@
-use std::{ffi::CString, fs, ptr};
+use std::{ffi::CString, fs, path::Path, ptr};
 use std::sync::atomic::{AtomicU32, Ordering};
 
+use crate::gldebug;
+
 static CURRENT_PROGRAM: AtomicU32 = AtomicU32::new(0);
 
 pub fn set_current_program(program: u32) {
@@ -11,8 +13,36 @@ pub fn get_current_program() -> u32 {
     CURRENT_PROGRAM.load(Ordering::Relaxed)
 }
 
+/// Reads a shader, pasting in any `#include "file"` lines, with paths
+/// relative to the including file. Each file is only pasted once.
+pub fn read_source(path: &Path) -> Result<String, String> {
+    let mut seen = vec![];
+    read_with_includes(path, &mut seen)
+}
+
+fn read_with_includes(path: &Path, seen: &mut Vec<std::path::PathBuf>) -> Result<String, String> {
+    if seen.iter().any(|p| p == path) {
+        return Ok(String::new());
+    }
+    seen.push(path.to_path_buf());
+    let source = fs::read_to_string(path).map_err(|e| format!("Failed to read '{}': {}", path.display(), e))?;
+    let mut out = String::with_capacity(source.len());
+    for line in source.lines() {
+        match line.trim().strip_prefix("#include") {
+            Some(rest) => {
+                let name = rest.trim().trim_matches('"');
+                let included = path.parent().unwrap_or(Path::new(".")).join(name);
+                out.push_str(&read_with_includes(&included, seen)?);
+            }
+            None => out.push_str(line),
+        }
+        out.push('\n');
+    }
+    Ok(out)
+}
+
 pub fn load_shader(path: &str, shader_type: u32) -> u32 {
-    let source = fs::read_to_string(path).expect(&format!("Failed to read '{}'", path));
+    let source = read_source(Path::new(path)).unwrap_or_else(|e| panic!("{e}"));
     let source_c = CString::new(source.clone()).expect("CString failed");
 
     unsafe {
@@ -38,26 +68,50 @@ pub fn load_shader(path: &str, shader_type: u32) -> u32 {
                 source
             );
         }
+        gldebug::label(gl::SHADER, shader, path);
         shader
     }
 }
 
 pub fn create_program() -> u32 {
+    build_program("lab2.vert", "lab2.frag", "lab2 program")
+}
+
+/// Compiles and links a vertex and fragment shader pair.
+pub fn build_program(vertex_path: &str, fragment_path: &str, label: &str) -> u32 {
+    link_program(&[(vertex_path, gl::VERTEX_SHADER), (fragment_path, gl::FRAGMENT_SHADER)], label)
+}
+
+/// As `build_program`, with a geometry shader in between.
+pub fn build_program_with_geometry(vertex_path: &str, geometry_path: &str, fragment_path: &str, label: &str) -> u32 {
+    link_program(
+        &[
+            (vertex_path, gl::VERTEX_SHADER),
+            (geometry_path, gl::GEOMETRY_SHADER),
+            (fragment_path, gl::FRAGMENT_SHADER),
+        ],
+        label,
+    )
+}
+
+fn link_program(stages: &[(&str, u32)], label: &str) -> u32 {
     unsafe {
-        let vertex_shader = load_shader("lab2.vert", gl::VERTEX_SHADER);
-        let fragment_shader = load_shader("lab2.frag", gl::FRAGMENT_SHADER);
+        let shaders: Vec<u32> = stages.iter().map(|&(path, shader_type)| load_shader(path, shader_type)).collect();
 
         let program = gl::CreateProgram();
-        gl::AttachShader(program, vertex_shader);
-        gl::AttachShader(program, fragment_shader);
+        for &shader in &shaders {
+            gl::AttachShader(program, shader);
+        }
         gl::LinkProgram(program);
 
         let mut status = 0;
         gl::GetProgramiv(program, gl::LINK_STATUS, &mut status);
         assert!(status == gl::TRUE as i32, "Program link failed");
 
-        gl::DeleteShader(vertex_shader);
-        gl::DeleteShader(fragment_shader);
+        for shader in shaders {
+            gl::DeleteShader(shader);
+        }
+        gldebug::label(gl::PROGRAM, program, label);
         program
     }
-}
\ No newline at end of file
+}
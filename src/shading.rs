@@ -0,0 +1,60 @@
+use std::fmt;
+
+/// How the lit programs light a surface. They all take the same attributes
+/// and uniforms, so any of them can draw any scene.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ShadingModel {
+    /// One normal per triangle.
+    Flat,
+    /// Lit per vertex, colours interpolated.
+    Gouraud,
+    /// Lit per fragment with interpolated normals.
+    #[default]
+    Phong,
+    /// As `Phong`, with the half-vector highlight.
+    BlinnPhong,
+}
+
+impl ShadingModel {
+    pub const ALL: [ShadingModel; 4] = [ShadingModel::Flat, ShadingModel::Gouraud, ShadingModel::Phong, ShadingModel::BlinnPhong];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ShadingModel::Flat => "flat",
+            ShadingModel::Gouraud => "gouraud",
+            ShadingModel::Phong => "phong",
+            ShadingModel::BlinnPhong => "blinn-phong",
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|model| model.name() == name)
+    }
+
+    /// The model the runtime key switches to next.
+    pub fn next(self) -> ShadingModel {
+        let i = Self::ALL.iter().position(|&model| model == self).unwrap();
+        Self::ALL[(i + 1) % Self::ALL.len()]
+    }
+
+    /// Vertex and fragment shader paths.
+    pub fn shaders(self) -> (&'static str, &'static str) {
+        match self {
+            ShadingModel::Flat => ("shaders/phong.vert", "shaders/flat.frag"),
+            ShadingModel::Gouraud => ("shaders/gouraud.vert", "shaders/gouraud.frag"),
+            ShadingModel::Phong => ("shaders/phong.vert", "shaders/phong.frag"),
+            ShadingModel::BlinnPhong => ("shaders/phong.vert", "shaders/blinn_phong.frag"),
+        }
+    }
+}
+
+impl fmt::Display for ShadingModel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ShadingModel::Flat => "flat",
+            ShadingModel::Gouraud => "Gouraud",
+            ShadingModel::Phong => "Phong",
+            ShadingModel::BlinnPhong => "Blinn-Phong",
+        })
+    }
+}
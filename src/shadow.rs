@@ -0,0 +1,585 @@
+//! Shadow maps. Each frame every directional and spot light that casts
+//! shadows renders the scene's depth from its own point of view into one
+//! layer of a depth texture array, and every such point light renders its
+//! distance to the scene into a cube of a cube map array. The lit shaders
+//! compare against both through `shaders/shadows.glsl`, softened with
+//! percentage-closer filtering.
+
+use std::ffi::CStr;
+
+use gl::types::{GLint, GLuint};
+use glam::{Mat4, Vec3, Vec4};
+
+use crate::{framebuffer, gldebug};
+use crate::light::{self, Light, LightKind};
+use crate::scene::Scene;
+use crate::shader;
+
+/// Must match `MAX_SHADOWS` in `shaders/shadows.glsl`.
+pub const MAX_SHADOWS: usize = 4;
+
+/// Must match `MAX_POINT_SHADOWS` in `shaders/shadows.glsl`.
+pub const MAX_POINT_SHADOWS: usize = 4;
+
+/// The texture unit the `shadowmaps` sampler reads, after the material's.
+pub const SHADOW_UNIT: u32 = 2;
+
+/// The texture unit the `pointshadowmaps` sampler reads.
+pub const POINT_SHADOW_UNIT: u32 = 3;
+
+/// Point light shadows reach out to the range, or this far without one.
+const POINT_FAR: f32 = 20.0;
+
+const POINT_NEAR: f32 = 0.05;
+
+/// Spot light shadows reach out to the range, or this far without one.
+const SPOT_FAR: f32 = 20.0;
+
+const SPOT_NEAR: f32 = 0.05;
+
+/// How the six faces of a point light's cube shadow map get drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CubePasses {
+    /// One pass per light, with a geometry shader sending each triangle
+    /// to every face.
+    #[default]
+    Single,
+    /// A pass per face, for when layered rendering isn't available.
+    Six,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ShadowSettings {
+    /// Width and height of each shadow map in texels.
+    pub resolution: u32,
+    /// Shadow map depth subtracted before comparing, to stop surfaces
+    /// shadowing themselves. The shaders scale it up at grazing angles.
+    pub bias: f32,
+    /// Half-width of the square of texels averaged for soft edges: 0 for
+    /// a single (bilinear) comparison, 1 for 3x3 and so on. For point
+    /// lights, 0 is one comparison and anything else 20 spread this many
+    /// texels apart.
+    pub pcf_radius: u32,
+    /// Width and height of each face of a point light's cube map.
+    pub cube_resolution: u32,
+    pub cube_passes: CubePasses,
+}
+
+impl Default for ShadowSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 1024,
+            bias: 0.0005,
+            pcf_radius: 1,
+            cube_resolution: 512,
+            cube_passes: CubePasses::default(),
+        }
+    }
+}
+
+/// Where a light sees the scene from, for rendering its shadow map.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightCamera {
+    pub view: Mat4,
+    pub projection: Mat4,
+    /// Depth range of a perspective projection, to linearize it for the
+    /// debug view. `None` for orthographic ones, which are linear already.
+    pub perspective: Option<(f32, f32)>,
+}
+
+impl LightCamera {
+    /// The camera for a directional or spot light over a scene within
+    /// `bounds` (centre and radius). A directional light looks at the
+    /// whole of it, a spot light through its outer cone.
+    pub fn new(light: &Light, (centre, radius): (Vec3, f32)) -> Self {
+        let direction = light.direction.normalize();
+        // look_at needs an up vector that isn't along the direction.
+        let up = if direction.cross(Vec3::Y).length_squared() < 1e-6 { Vec3::Z } else { Vec3::Y };
+        let radius = radius.max(1e-3);
+        match light.kind {
+            LightKind::Spot => {
+                let far = if light.range > 0.0 {
+                    light.range
+                } else {
+                    (light.position.distance(centre) + radius).min(SPOT_FAR)
+                };
+                let far = far.max(SPOT_NEAR * 2.0);
+                // A little wider than the cone, so its edge isn't clipped.
+                let fov = (2.0 * light.outer_cone + 5.0).min(170.0).to_radians();
+                Self {
+                    view: Mat4::look_at_rh(light.position, light.position + direction, up),
+                    projection: Mat4::perspective_rh_gl(fov, 1.0, SPOT_NEAR, far),
+                    perspective: Some((SPOT_NEAR, far)),
+                }
+            }
+            LightKind::Directional | LightKind::Point => {
+                let eye = centre - direction * radius * 2.0;
+                Self {
+                    view: Mat4::look_at_rh(eye, centre, up),
+                    projection: Mat4::orthographic_rh_gl(-radius, radius, -radius, radius, radius, radius * 3.0),
+                    perspective: None,
+                }
+            }
+        }
+    }
+
+    pub fn matrix(&self) -> Mat4 {
+        self.projection * self.view
+    }
+}
+
+/// How far a point light's shadows reach, which its cube map stores
+/// distances as a fraction of.
+pub fn point_far(light: &Light) -> f32 {
+    if light.range > 0.0 { light.range } else { POINT_FAR }
+}
+
+/// View-projection matrices for the faces of a cube map around `position`
+/// in GL's order, +X, -X, +Y, -Y, +Z, -Z, each oriented as the cube map
+/// is sampled.
+pub fn cube_face_matrices(position: Vec3, far: f32) -> [Mat4; 6] {
+    let projection = Mat4::perspective_rh_gl(std::f32::consts::FRAC_PI_2, 1.0, POINT_NEAR, far);
+    // Cube map faces are seen from inside with t running downwards, hence
+    // the upside-down ups.
+    let faces = [
+        (Vec3::X, Vec3::NEG_Y),
+        (Vec3::NEG_X, Vec3::NEG_Y),
+        (Vec3::Y, Vec3::Z),
+        (Vec3::NEG_Y, Vec3::NEG_Z),
+        (Vec3::Z, Vec3::NEG_Y),
+        (Vec3::NEG_Z, Vec3::NEG_Y),
+    ];
+    faces.map(|(direction, up)| projection * Mat4::look_at_rh(position, position + direction, up))
+}
+
+/// The depth textures the shadow casters render into, and the programs to
+/// fill and show them.
+pub struct ShadowMaps {
+    pub settings: ShadowSettings,
+    fbo: GLuint,
+    texture: GLuint,
+    /// Reads raw depth for the debug view, where the texture's own
+    /// parameters would compare it.
+    debug_sampler: GLuint,
+    depth_program: GLuint,
+    light_matrix_loc: GLint,
+    debug_program: GLuint,
+    /// Bound for the debug quad, whose corners come from `gl_VertexID`.
+    empty_vao: GLuint,
+    /// The lights' cameras from the last `render`, one per layer.
+    cameras: Vec<LightCamera>,
+    cube_fbo: GLuint,
+    cube_texture: GLuint,
+    /// With a geometry shader for `CubePasses::Single`, without for `Six`.
+    cube_program: GLuint,
+    /// The point lights from the last `render`, one per cube: world-space
+    /// position and, in `w`, `point_far`.
+    point_lights: Vec<Vec4>,
+}
+
+impl ShadowMaps {
+    /// Needs a current context. The resolution is clamped to what the
+    /// driver allows.
+    pub fn new(mut settings: ShadowSettings) -> Result<Self, String> {
+        unsafe {
+            let mut max_size: GLint = 0;
+            gl::GetIntegerv(gl::MAX_TEXTURE_SIZE, &mut max_size);
+            settings.resolution = settings.resolution.min(max_size as u32);
+            let size = settings.resolution as i32;
+
+            let mut texture = 0;
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, texture);
+            gl::TexStorage3D(gl::TEXTURE_2D_ARRAY, 1, gl::DEPTH_COMPONENT24, size, size, MAX_SHADOWS as i32);
+            // Linear filtering makes each comparison a bilinear 2x2 PCF.
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+            // Outside the map counts as lit.
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_BORDER as i32);
+            gl::TexParameteri(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_BORDER as i32);
+            gl::TexParameterfv(gl::TEXTURE_2D_ARRAY, gl::TEXTURE_BORDER_COLOR, [1.0f32; 4].as_ptr());
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, 0);
+            gldebug::label(gl::TEXTURE, texture, "Shadow maps");
+
+            let mut debug_sampler = 0;
+            gl::GenSamplers(1, &mut debug_sampler);
+            gl::SamplerParameteri(debug_sampler, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+            gl::SamplerParameteri(debug_sampler, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+            gl::SamplerParameteri(debug_sampler, gl::TEXTURE_COMPARE_MODE, gl::NONE as i32);
+
+            let mut fbo = 0;
+            gl::GenFramebuffers(1, &mut fbo);
+            let mut empty_vao = 0;
+            gl::GenVertexArrays(1, &mut empty_vao);
+
+            let depth_program = shader::build_program("shaders/shadow.vert", "shaders/shadow.frag", "shadow map program");
+            let debug_program =
+                shader::build_program("shaders/shadow_debug.vert", "shaders/shadow_debug.frag", "shadow map debug program");
+            // Owned from here, so an early return deletes what's been made;
+            // the cube map names stay 0 until they exist.
+            let mut maps = Self {
+                settings,
+                fbo,
+                texture,
+                debug_sampler,
+                depth_program,
+                light_matrix_loc: gl::GetUniformLocation(depth_program, c"light_matrix".as_ptr() as *const _),
+                debug_program,
+                empty_vao,
+                cameras: vec![],
+                cube_fbo: 0,
+                cube_texture: 0,
+                cube_program: 0,
+                point_lights: vec![],
+            };
+            (maps.cube_fbo, maps.cube_texture) = Self::create_cube_maps(&mut maps.settings)?;
+            maps.cube_program = match maps.settings.cube_passes {
+                CubePasses::Single => shader::build_program_with_geometry(
+                    "shaders/point_shadow.vert",
+                    "shaders/point_shadow.geom",
+                    "shaders/point_shadow.frag",
+                    "point shadow program",
+                ),
+                CubePasses::Six => {
+                    shader::build_program("shaders/point_shadow.vert", "shaders/point_shadow.frag", "point shadow program")
+                }
+            };
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture, 0, 0);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                return Err(format!("shadow map framebuffer incomplete: {}", framebuffer::status_message(status)));
+            }
+            gldebug::label(gl::FRAMEBUFFER, fbo, "Shadow map framebuffer");
+            Ok(maps)
+        }
+    }
+
+    /// The framebuffer and cube map array for point lights. Clamps the
+    /// cube resolution and falls back to six passes if the framebuffer
+    /// can't take the whole array at once.
+    unsafe fn create_cube_maps(settings: &mut ShadowSettings) -> Result<(GLuint, GLuint), String> {
+        unsafe {
+            let mut max_size: GLint = 0;
+            gl::GetIntegerv(gl::MAX_CUBE_MAP_TEXTURE_SIZE, &mut max_size);
+            settings.cube_resolution = settings.cube_resolution.min(max_size as u32);
+            let size = settings.cube_resolution as i32;
+
+            let mut texture = 0;
+            gl::GenTextures(1, &mut texture);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, texture);
+            gl::TexStorage3D(gl::TEXTURE_CUBE_MAP_ARRAY, 1, gl::DEPTH_COMPONENT24, size, size, 6 * MAX_POINT_SHADOWS as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_MIN_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_COMPARE_MODE, gl::COMPARE_REF_TO_TEXTURE as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP_ARRAY, gl::TEXTURE_COMPARE_FUNC, gl::LEQUAL as i32);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, 0);
+            // Filter across face edges rather than clamping at them.
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            gldebug::label(gl::TEXTURE, texture, "Point shadow maps");
+
+            let mut fbo = 0;
+            gl::GenFramebuffers(1, &mut fbo);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, fbo);
+            gl::DrawBuffer(gl::NONE);
+            gl::ReadBuffer(gl::NONE);
+            let complete = || gl::CheckFramebufferStatus(gl::FRAMEBUFFER) == gl::FRAMEBUFFER_COMPLETE;
+
+            if settings.cube_passes == CubePasses::Single {
+                let mut max_vertices: GLint = 0;
+                gl::GetIntegerv(gl::MAX_GEOMETRY_OUTPUT_VERTICES, &mut max_vertices);
+                gl::FramebufferTexture(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture, 0);
+                if max_vertices < 18 || !complete() {
+                    println!("Layered rendering unavailable, drawing point shadows a face at a time");
+                    settings.cube_passes = CubePasses::Six;
+                }
+            }
+            if settings.cube_passes == CubePasses::Six {
+                gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, texture, 0, 0);
+            }
+            let status = gl::CheckFramebufferStatus(gl::FRAMEBUFFER);
+            gl::BindFramebuffer(gl::FRAMEBUFFER, 0);
+            if status != gl::FRAMEBUFFER_COMPLETE {
+                gl::DeleteFramebuffers(1, &fbo);
+                gl::DeleteTextures(1, &texture);
+                return Err(format!("point shadow framebuffer incomplete: {}", framebuffer::status_message(status)));
+            }
+            gldebug::label(gl::FRAMEBUFFER, fbo, "Point shadow framebuffer");
+            Ok((fbo, texture))
+        }
+    }
+
+    /// How many 2D layers the last `render` filled.
+    pub fn layers(&self) -> usize {
+        self.cameras.len()
+    }
+
+    /// How many point light cubes the last `render` filled.
+    pub fn cubes(&self) -> usize {
+        self.point_lights.len()
+    }
+
+    /// Renders a shadow map for each of the scene's `light::shadow_casters`
+    /// and a cube for each of its `light::point_shadow_casters`, leaving
+    /// the framebuffer, viewport and program as they were. Call
+    /// `scene.update` first.
+    pub fn render(&mut self, scene: &Scene) {
+        self.cameras.clear();
+        self.point_lights.clear();
+        let Some(bounds) = scene.bounds() else {
+            return;
+        };
+        self.cameras = light::shadow_casters(&scene.lights).map(|light| LightCamera::new(light, bounds)).collect();
+        self.point_lights =
+            light::point_shadow_casters(&scene.lights).map(|light| light.position.extend(point_far(light))).collect();
+
+        unsafe {
+            let mut framebuffer: GLint = 0;
+            gl::GetIntegerv(gl::DRAW_FRAMEBUFFER_BINDING, &mut framebuffer);
+            let mut viewport = [0; 4];
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            let program = shader::get_current_program();
+
+            let size = self.settings.resolution as i32;
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.fbo);
+            gl::Viewport(0, 0, size, size);
+            gl::UseProgram(self.depth_program);
+            shader::set_current_program(self.depth_program);
+            // Slope-scaled offset on top of the shaders' bias, for the
+            // steep faces the constant bias can't cover.
+            gl::Enable(gl::POLYGON_OFFSET_FILL);
+            gl::PolygonOffset(2.0, 4.0);
+            for (layer, camera) in self.cameras.iter().enumerate() {
+                gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, self.texture, 0, layer as i32);
+                gl::Clear(gl::DEPTH_BUFFER_BIT);
+                gl::UniformMatrix4fv(self.light_matrix_loc, 1, gl::FALSE, camera.matrix().to_cols_array().as_ptr());
+                scene.draw_shadow_casters();
+            }
+            gl::Disable(gl::POLYGON_OFFSET_FILL);
+
+            if !self.point_lights.is_empty() {
+                self.render_cubes(scene);
+            }
+
+            gl::BindFramebuffer(gl::FRAMEBUFFER, framebuffer as GLuint);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl::UseProgram(program);
+            shader::set_current_program(program);
+        }
+    }
+
+    /// Draws each point light's distances to the scene into its cube. The
+    /// fragment shader writes the depth itself, so polygon offset has no
+    /// effect and the bias is all down to the lit shaders.
+    unsafe fn render_cubes(&self, scene: &Scene) {
+        let program = self.cube_program;
+        let loc = |name: &CStr| unsafe { gl::GetUniformLocation(program, name.as_ptr() as *const _) };
+        let (light_loc, light_matrix_loc) = (loc(c"light"), loc(c"light_matrix"));
+        let (face_matrices_loc, first_layer_loc) = (loc(c"face_matrices"), loc(c"first_layer"));
+        let size = self.settings.cube_resolution as i32;
+        unsafe {
+            gl::BindFramebuffer(gl::FRAMEBUFFER, self.cube_fbo);
+            gl::Viewport(0, 0, size, size);
+            gl::UseProgram(program);
+            shader::set_current_program(program);
+            if self.settings.cube_passes == CubePasses::Single {
+                // The whole array is attached, so this clears every face.
+                gl::Clear(gl::DEPTH_BUFFER_BIT);
+            }
+            for (cube, light) in self.point_lights.iter().enumerate() {
+                gl::Uniform4f(light_loc, light.x, light.y, light.z, light.w);
+                let faces = cube_face_matrices(light.truncate(), light.w);
+                match self.settings.cube_passes {
+                    CubePasses::Single => {
+                        let matrices: Vec<f32> = faces.iter().flat_map(|m| m.to_cols_array()).collect();
+                        gl::UniformMatrix4fv(face_matrices_loc, 6, gl::FALSE, matrices.as_ptr());
+                        gl::Uniform1i(first_layer_loc, cube as i32 * 6);
+                        gl::UniformMatrix4fv(light_matrix_loc, 1, gl::FALSE, Mat4::IDENTITY.to_cols_array().as_ptr());
+                        scene.draw_shadow_casters();
+                    }
+                    CubePasses::Six => {
+                        for (face, matrix) in faces.iter().enumerate() {
+                            let layer = (cube * 6 + face) as i32;
+                            gl::FramebufferTextureLayer(gl::FRAMEBUFFER, gl::DEPTH_ATTACHMENT, self.cube_texture, 0, layer);
+                            gl::Clear(gl::DEPTH_BUFFER_BIT);
+                            gl::UniformMatrix4fv(light_matrix_loc, 1, gl::FALSE, matrix.to_cols_array().as_ptr());
+                            scene.draw_shadow_casters();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Binds the maps and sets the `shadows.glsl` uniforms on the current
+    /// program, for a camera with view matrix `view`.
+    pub fn bind(&self, view: Mat4) {
+        // Shading happens in view space, so go from there to the shadow
+        // map's [0, 1] texture coordinates and depth.
+        let to_texture = Mat4::from_translation(Vec3::splat(0.5)) * Mat4::from_scale(Vec3::splat(0.5));
+        let view_inverse = view.inverse();
+        let matrices: Vec<f32> =
+            self.cameras.iter().flat_map(|camera| (to_texture * camera.matrix() * view_inverse).to_cols_array()).collect();
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + SHADOW_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.texture);
+            gl::ActiveTexture(gl::TEXTURE0 + POINT_SHADOW_UNIT);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP_ARRAY, self.cube_texture);
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            let program = shader::get_current_program();
+            let loc = |name: &CStr| gl::GetUniformLocation(program, name.as_ptr() as *const _);
+            let sampler_loc = loc(c"shadowmaps");
+            if sampler_loc != -1 {
+                gl::Uniform1i(sampler_loc, SHADOW_UNIT as i32);
+            }
+            let point_sampler_loc = loc(c"pointshadowmaps");
+            if point_sampler_loc != -1 {
+                gl::Uniform1i(point_sampler_loc, POINT_SHADOW_UNIT as i32);
+            }
+            let point_lights_loc = loc(c"point_shadows");
+            if point_lights_loc != -1 && !self.point_lights.is_empty() {
+                gl::Uniform4fv(point_lights_loc, self.point_lights.len() as i32, self.point_lights.as_ptr() as *const f32);
+            }
+            let matrices_loc = loc(c"shadow_matrices");
+            if matrices_loc != -1 && !self.cameras.is_empty() {
+                gl::UniformMatrix4fv(matrices_loc, self.cameras.len() as i32, gl::FALSE, matrices.as_ptr());
+            }
+            let bias_loc = loc(c"shadow_bias");
+            if bias_loc != -1 {
+                gl::Uniform1f(bias_loc, self.settings.bias);
+            }
+            let pcf_loc = loc(c"shadow_pcf");
+            if pcf_loc != -1 {
+                gl::Uniform1i(pcf_loc, self.settings.pcf_radius as i32);
+            }
+        }
+    }
+
+    /// Shows layer `layer` as a greyscale square in the bottom left corner
+    /// of a `width` x `height` framebuffer, near black and far white.
+    pub fn draw_debug(&self, layer: usize, width: u16, height: u16) {
+        let Some(camera) = self.cameras.get(layer) else {
+            return;
+        };
+        let size = (width.min(height) / 3) as i32;
+        unsafe {
+            let mut viewport = [0; 4];
+            gl::GetIntegerv(gl::VIEWPORT, viewport.as_mut_ptr());
+            let program = shader::get_current_program();
+
+            gl::Viewport(0, 0, size, size);
+            gl::Disable(gl::DEPTH_TEST);
+            gl::UseProgram(self.debug_program);
+            shader::set_current_program(self.debug_program);
+            gl::ActiveTexture(gl::TEXTURE0 + SHADOW_UNIT);
+            gl::BindTexture(gl::TEXTURE_2D_ARRAY, self.texture);
+            gl::BindSampler(SHADOW_UNIT, self.debug_sampler);
+            gl::ActiveTexture(gl::TEXTURE0);
+
+            let loc = |name: &CStr| gl::GetUniformLocation(self.debug_program, name.as_ptr() as *const _);
+            gl::Uniform1i(loc(c"depthmaps"), SHADOW_UNIT as i32);
+            gl::Uniform1i(loc(c"layer"), layer as i32);
+            // (0, 0) for orthographic layers.
+            let (near, far) = camera.perspective.unwrap_or((0.0, 0.0));
+            gl::Uniform2f(loc(c"depth_range"), near, far);
+            gl::BindVertexArray(self.empty_vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            gl::BindVertexArray(0);
+
+            gl::BindSampler(SHADOW_UNIT, 0);
+            gl::Enable(gl::DEPTH_TEST);
+            gl::Viewport(viewport[0], viewport[1], viewport[2], viewport[3]);
+            gl::UseProgram(program);
+            shader::set_current_program(program);
+        }
+    }
+}
+
+impl Drop for ShadowMaps {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteFramebuffers(2, [self.fbo, self.cube_fbo].as_ptr());
+            gl::DeleteTextures(2, [self.texture, self.cube_texture].as_ptr());
+            gl::DeleteProgram(self.cube_program);
+            gl::DeleteSamplers(1, &self.debug_sampler);
+            gl::DeleteVertexArrays(1, &self.empty_vao);
+            gl::DeleteProgram(self.depth_program);
+            gl::DeleteProgram(self.debug_program);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Where `point` lands in the light's normalized device coordinates.
+    fn ndc(camera: &LightCamera, point: Vec3) -> Vec3 {
+        camera.matrix().project_point3(point)
+    }
+
+    fn inside(p: Vec3) -> bool {
+        p.abs().max_element() <= 1.0 + 1e-5
+    }
+
+    #[test]
+    fn directional_cameras_see_the_whole_scene() {
+        let bounds = (Vec3::new(1.0, 2.0, 0.0), 3.0);
+        for direction in [Vec3::NEG_Y, Vec3::new(1.0, -1.0, 0.5), Vec3::X] {
+            let camera = LightCamera::new(&Light::directional(direction), bounds);
+            assert_eq!(camera.perspective, None);
+            for offset in [Vec3::ZERO, Vec3::X, Vec3::NEG_Y, Vec3::Z, Vec3::ONE.normalize()] {
+                let p = ndc(&camera, bounds.0 + offset * 3.0);
+                assert!(inside(p), "{direction}: {p}");
+            }
+            // Points nearer the light are nearer the camera.
+            let near = ndc(&camera, bounds.0 - direction.normalize());
+            let far = ndc(&camera, bounds.0 + direction.normalize());
+            assert!(near.z < far.z);
+        }
+    }
+
+    #[test]
+    fn cube_faces_match_how_cube_maps_are_sampled() {
+        let centre = Vec3::new(1.0, 2.0, 3.0);
+        let faces = cube_face_matrices(centre, 10.0);
+        // (major axis, sc axis, tc axis) for each face, from the GL spec's
+        // table.
+        let sampling = [
+            (Vec3::X, Vec3::NEG_Z, Vec3::NEG_Y),
+            (Vec3::NEG_X, Vec3::Z, Vec3::NEG_Y),
+            (Vec3::Y, Vec3::X, Vec3::Z),
+            (Vec3::NEG_Y, Vec3::X, Vec3::NEG_Z),
+            (Vec3::Z, Vec3::X, Vec3::NEG_Y),
+            (Vec3::NEG_Z, Vec3::NEG_X, Vec3::NEG_Y),
+        ];
+        for (matrix, (axis, s, t)) in faces.iter().zip(sampling) {
+            // Off-centre, but still on this face.
+            let direction = axis * 2.0 + Vec3::new(0.3, -0.6, 0.9) * (Vec3::ONE - axis.abs());
+            let p = matrix.project_point3(centre + direction);
+            let (major, sc, tc) = (direction.dot(axis), direction.dot(s), direction.dot(t));
+            let expected = Vec3::new(sc / major, tc / major, p.z);
+            assert!(p.abs_diff_eq(expected, 1e-5) && inside(p), "{axis}: {p} != {expected}");
+        }
+    }
+
+    #[test]
+    fn spot_cameras_look_down_the_cone() {
+        let spot = Light::spot(Vec3::new(0.0, 2.0, 0.0), Vec3::NEG_Y, 4.0);
+        let camera = LightCamera::new(&spot, (Vec3::ZERO, 1.0));
+        assert_eq!(camera.perspective, Some((SPOT_NEAR, 4.0)));
+        let centre = ndc(&camera, Vec3::new(0.0, 1.0, 0.0));
+        assert!(centre.truncate().length() < 1e-5 && inside(centre), "{centre}");
+        // The edge of the outer cone, 1 below the light, is in view.
+        let edge = ndc(&camera, Vec3::new(30f32.to_radians().tan(), 1.0, 0.0));
+        assert!(inside(edge), "{edge}");
+        assert!(!inside(ndc(&camera, Vec3::new(0.0, -3.0, 0.0))), "beyond the range");
+    }
+}
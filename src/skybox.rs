@@ -0,0 +1,225 @@
+//! Skyboxes: a cube map loaded from six face images or one
+//! equirectangular panorama, drawn behind the scene and sampled by
+//! reflective and refractive materials through `shaders/environment.glsl`.
+
+use std::f32::consts::PI;
+use std::path::{Path, PathBuf};
+
+use gl::types::{GLint, GLuint};
+use glam::{Mat4, Vec3};
+use serde::{Deserialize, Serialize};
+
+use crate::image::Image;
+use crate::shader;
+use crate::texture::{self, CubeMap};
+
+/// The texture unit the `environment` and `skybox` samplers read, after
+/// the shadow maps'.
+pub const ENVIRONMENT_UNIT: u32 = 4;
+
+/// Where a skybox's images come from, relative to the scene file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SkyboxDesc {
+    /// Square images for +X, -X, +Y, -Y, +Z and -Z, each as seen from
+    /// inside the cube with its top row towards +Y, or towards -Z for the
+    /// +Y face and +Z for the -Y face.
+    Faces(Vec<PathBuf>),
+    /// A 2:1 longitude-latitude panorama with -Z at its centre and +Y at
+    /// the top.
+    Equirectangular(PathBuf),
+}
+
+impl SkyboxDesc {
+    pub fn check(&self) -> Result<(), String> {
+        match self {
+            SkyboxDesc::Faces(paths) if paths.len() != 6 => Err(format!("a skybox has 6 faces, not {}", paths.len())),
+            _ => Ok(()),
+        }
+    }
+
+    /// Decodes the images into the six faces of a cube map.
+    pub fn load(&self, dir: &Path) -> Result<[Image; 6], String> {
+        self.check()?;
+        match self {
+            SkyboxDesc::Faces(paths) => {
+                let faces = paths.iter().map(|path| texture::decode(&dir.join(path))).collect::<Result<Vec<_>, _>>()?;
+                Ok(faces.try_into().unwrap_or_else(|_| unreachable!("checked there are six")))
+            }
+            SkyboxDesc::Equirectangular(path) => {
+                let panorama = texture::decode(&dir.join(path))?;
+                Ok(equirectangular_to_faces(&panorama, (panorama.width / 4).max(1)))
+            }
+        }
+    }
+}
+
+/// (major axis, s axis, t axis) of each face in GL's order: the direction
+/// through texel (s, t) of a face, both from -1 to 1, is
+/// `major + s * s_axis + t * t_axis`, with t = -1 the first row.
+const FACE_AXES: [(Vec3, Vec3, Vec3); 6] = [
+    (Vec3::X, Vec3::NEG_Z, Vec3::NEG_Y),
+    (Vec3::NEG_X, Vec3::Z, Vec3::NEG_Y),
+    (Vec3::Y, Vec3::X, Vec3::Z),
+    (Vec3::NEG_Y, Vec3::X, Vec3::NEG_Z),
+    (Vec3::Z, Vec3::X, Vec3::NEG_Y),
+    (Vec3::NEG_Z, Vec3::NEG_X, Vec3::NEG_Y),
+];
+
+/// Resamples a panorama, as `SkyboxDesc::Equirectangular` describes, into
+/// `size` by `size` cube faces with bilinear filtering.
+pub fn equirectangular_to_faces(panorama: &Image, size: u32) -> [Image; 6] {
+    FACE_AXES.map(|(major, s_axis, t_axis)| {
+        let mut pixels = Vec::with_capacity((size * size * 4) as usize);
+        for row in 0..size {
+            for column in 0..size {
+                let s = (column as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let t = (row as f32 + 0.5) / size as f32 * 2.0 - 1.0;
+                let direction = (major + s * s_axis + t * t_axis).normalize();
+                let u = 0.5 + direction.x.atan2(-direction.z) / (2.0 * PI);
+                let v = 0.5 - direction.y.clamp(-1.0, 1.0).asin() / PI;
+                pixels.extend(sample_bilinear(panorama, u, v));
+            }
+        }
+        Image {
+            width: size,
+            height: size,
+            pixels,
+        }
+    })
+}
+
+/// The colour at (u, v), 0 to 1 from the top left, wrapping around
+/// horizontally and clamped vertically.
+fn sample_bilinear(image: &Image, u: f32, v: f32) -> [u8; 4] {
+    let (width, height) = (image.width as i64, image.height as i64);
+    let x = u * width as f32 - 0.5;
+    let y = (v * height as f32 - 0.5).clamp(0.0, (height - 1) as f32);
+    let (x0, y0) = (x.floor(), y.floor());
+    let (fx, fy) = (x - x0, y - y0);
+    let texel = |x: i64, y: i64| {
+        let i = ((y.min(height - 1) * width + x.rem_euclid(width)) * 4) as usize;
+        &image.pixels[i..i + 4]
+    };
+    let (x0, y0) = (x0 as i64, y0 as i64);
+    let corners = [texel(x0, y0), texel(x0 + 1, y0), texel(x0, y0 + 1), texel(x0 + 1, y0 + 1)];
+    let weights = [(1.0 - fx) * (1.0 - fy), fx * (1.0 - fy), (1.0 - fx) * fy, fx * fy];
+    std::array::from_fn(|channel| {
+        let value: f32 = corners.iter().zip(weights).map(|(texel, weight)| texel[channel] as f32 * weight).sum();
+        value.round() as u8
+    })
+}
+
+/// A cube map and the program to draw it as the background.
+pub struct Skybox {
+    pub cube: CubeMap,
+    program: GLuint,
+    sky_matrix_loc: GLint,
+    /// Bound for the background quad, whose corners come from
+    /// `gl_VertexID`.
+    empty_vao: GLuint,
+}
+
+impl Skybox {
+    /// Needs a current context.
+    pub fn load(desc: &SkyboxDesc, dir: &Path) -> Result<Self, String> {
+        let faces = desc.load(dir)?;
+        let cube = CubeMap::from_faces(&faces, "Skybox")?;
+        unsafe {
+            let program = shader::build_program("shaders/skybox.vert", "shaders/skybox.frag", "skybox program");
+            let mut empty_vao = 0;
+            gl::GenVertexArrays(1, &mut empty_vao);
+            Ok(Self {
+                cube,
+                program,
+                sky_matrix_loc: gl::GetUniformLocation(program, c"sky_matrix".as_ptr() as *const _),
+                empty_vao,
+            })
+        }
+    }
+
+    /// Fills in the background wherever the depth buffer is still clear,
+    /// so draw it after the opaque geometry and before anything blended.
+    /// Leaves the program and depth function as they were.
+    pub fn draw(&self, view: Mat4, projection: Mat4) {
+        let rotation = Mat4::from_mat3(glam::Mat3::from_mat4(view));
+        let sky_matrix = (projection * rotation).inverse();
+        unsafe {
+            let program = shader::get_current_program();
+            let mut depth_func: GLint = 0;
+            gl::GetIntegerv(gl::DEPTH_FUNC, &mut depth_func);
+
+            gl::DepthFunc(gl::LEQUAL);
+            gl::DepthMask(gl::FALSE);
+            gl::UseProgram(self.program);
+            shader::set_current_program(self.program);
+            gl::UniformMatrix4fv(self.sky_matrix_loc, 1, gl::FALSE, sky_matrix.to_cols_array().as_ptr());
+            let sampler_loc = gl::GetUniformLocation(self.program, c"skybox".as_ptr() as *const _);
+            gl::Uniform1i(sampler_loc, ENVIRONMENT_UNIT as i32);
+            self.cube.bind(ENVIRONMENT_UNIT);
+            gl::ActiveTexture(gl::TEXTURE0);
+            gl::BindVertexArray(self.empty_vao);
+            gl::DrawArrays(gl::TRIANGLE_STRIP, 0, 4);
+            gl::BindVertexArray(0);
+
+            gl::DepthMask(gl::TRUE);
+            gl::DepthFunc(depth_func as u32);
+            gl::UseProgram(program);
+            shader::set_current_program(program);
+        }
+    }
+}
+
+impl Drop for Skybox {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteVertexArrays(1, &self.empty_vao);
+            gl::DeleteProgram(self.program);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn face_axes_match_the_view_from_inside() {
+        for (major, s, t) in FACE_AXES {
+            // Seen from inside, s runs right and t down, so s x t points
+            // back at the viewer.
+            assert_eq!(s.cross(t), -major, "{major}");
+        }
+    }
+
+    #[test]
+    fn panoramas_land_on_the_right_faces() {
+        // Sky above the horizon, ground below, and a red stripe down the
+        // middle, which is -Z.
+        let (width, height) = (64, 32);
+        let mut pixels = vec![];
+        for y in 0..height {
+            for x in 0..width {
+                pixels.extend(match (x, y) {
+                    (30..=33, _) => [255, 0, 0, 255],
+                    (_, 0..16) => [0, 0, 255, 255],
+                    _ => [0, 255, 0, 255],
+                });
+            }
+        }
+        let faces = equirectangular_to_faces(&Image { width, height, pixels }, 8);
+        let texel = |face: usize, x: u32, y: u32| -> [u8; 4] {
+            let i = ((y * 8 + x) * 4) as usize;
+            faces[face].pixels[i..i + 4].try_into().unwrap()
+        };
+        assert!(faces.iter().all(|face| (face.width, face.height, face.pixels.len()) == (8, 8, 256)));
+        // The tops of the sides are sky and the bottoms ground.
+        for face in [0, 1, 4] {
+            assert_eq!((texel(face, 1, 0), texel(face, 1, 7)), ([0, 0, 255, 255], [0, 255, 0, 255]), "face {face}");
+        }
+        assert_eq!(texel(2, 0, 0), [0, 0, 255, 255]);
+        assert_eq!(texel(3, 0, 0), [0, 255, 0, 255]);
+        // The stripe runs down the middle of -Z and not +Z.
+        assert_eq!((texel(5, 3, 4), texel(5, 4, 4)), ([255, 0, 0, 255], [255, 0, 0, 255]));
+        assert_eq!(texel(4, 3, 4)[0], 0);
+    }
+}
@@ -0,0 +1,374 @@
+//! 2D and cube map textures: decoding PNG, JPEG and binary PPM files into
+//! `Image`s, and uploading them with a full mipmap chain.
+
+use std::path::{Path, PathBuf};
+
+use gl::types::{GLenum, GLuint};
+use serde::{Deserialize, Serialize};
+
+use crate::gldebug;
+use crate::image::Image;
+
+/// How texels are picked when a texture is drawn larger or smaller than
+/// its own size.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Filter {
+    /// Blocky up close, sparkly far away.
+    Nearest,
+    /// Bilinear within the nearest mip level.
+    Linear,
+    /// Bilinear, blended between the two nearest mip levels.
+    #[default]
+    Trilinear,
+}
+
+/// What happens to texture coordinates outside 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+pub enum Wrap {
+    #[default]
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+}
+
+impl Filter {
+    /// (minification, magnification) filters.
+    fn gl(self) -> (GLenum, GLenum) {
+        match self {
+            Filter::Nearest => (gl::NEAREST_MIPMAP_NEAREST, gl::NEAREST),
+            Filter::Linear => (gl::LINEAR_MIPMAP_NEAREST, gl::LINEAR),
+            Filter::Trilinear => (gl::LINEAR_MIPMAP_LINEAR, gl::LINEAR),
+        }
+    }
+}
+
+impl Wrap {
+    fn gl(self) -> GLenum {
+        match self {
+            Wrap::Repeat => gl::REPEAT,
+            Wrap::MirroredRepeat => gl::MIRRORED_REPEAT,
+            Wrap::ClampToEdge => gl::CLAMP_TO_EDGE,
+        }
+    }
+}
+
+/// An image file and how to sample it, as materials refer to textures.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct TextureMap {
+    pub path: PathBuf,
+    #[serde(default)]
+    pub filter: Filter,
+    #[serde(default)]
+    pub wrap: Wrap,
+}
+
+impl TextureMap {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            filter: Filter::default(),
+            wrap: Wrap::default(),
+        }
+    }
+}
+
+/// Reads an image file, telling the format from its first bytes rather
+/// than its name.
+pub fn decode(path: &Path) -> Result<Image, String> {
+    let bytes = std::fs::read(path).map_err(|e| format!("{}: {e}", path.display()))?;
+    let image = match bytes.as_slice() {
+        [0x89, b'P', b'N', b'G', ..] => decode_png(&bytes),
+        [0xff, 0xd8, ..] => decode_jpeg(&bytes),
+        [b'P', b'6', ..] => decode_ppm(&bytes),
+        _ => Err("not a PNG, JPEG or binary PPM file".into()),
+    };
+    image.map_err(|e| format!("{}: {e}", path.display()))
+}
+
+pub fn decode_png(bytes: &[u8]) -> Result<Image, String> {
+    let mut decoder = png::Decoder::new(bytes);
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|e| e.to_string())?;
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).map_err(|e| e.to_string())?;
+    buf.truncate(info.buffer_size());
+    let pixels = match info.color_type {
+        png::ColorType::Rgba => buf,
+        png::ColorType::Rgb => rgb_to_rgba(&buf),
+        png::ColorType::GrayscaleAlpha => buf.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect(),
+        png::ColorType::Grayscale => buf.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        png::ColorType::Indexed => return Err("palette wasn't expanded".into()),
+    };
+    Ok(Image {
+        width: info.width,
+        height: info.height,
+        pixels,
+    })
+}
+
+pub fn decode_jpeg(bytes: &[u8]) -> Result<Image, String> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let data = decoder.decode().map_err(|e| e.to_string())?;
+    let info = decoder.info().ok_or("JPEG has no frame")?;
+    let pixels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::RGB24 => rgb_to_rgba(&data),
+        jpeg_decoder::PixelFormat::L8 => data.iter().flat_map(|&l| [l, l, l, 255]).collect(),
+        // Big-endian, so the high byte comes first.
+        jpeg_decoder::PixelFormat::L16 => data.chunks_exact(2).flat_map(|l| [l[0], l[0], l[0], 255]).collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => return Err("CMYK JPEGs aren't supported".into()),
+    };
+    Ok(Image {
+        width: info.width as u32,
+        height: info.height as u32,
+        pixels,
+    })
+}
+
+/// Binary (`P6`) PPM with up to 8 bits per channel, as `Image::write_ppm`
+/// writes.
+pub fn decode_ppm(bytes: &[u8]) -> Result<Image, String> {
+    // Header fields are separated by whitespace, with `#` comments to the
+    // end of the line, and a single whitespace byte before the pixels.
+    let mut pos = 0;
+    let mut field = || -> Result<&[u8], String> {
+        loop {
+            match bytes.get(pos) {
+                Some(b) if b.is_ascii_whitespace() => pos += 1,
+                Some(b'#') => {
+                    while bytes.get(pos).is_some_and(|&b| b != b'\n') {
+                        pos += 1;
+                    }
+                }
+                Some(_) => break,
+                None => return Err("PPM header is cut short".into()),
+            }
+        }
+        let start = pos;
+        while bytes.get(pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+            pos += 1;
+        }
+        Ok(&bytes[start..pos])
+    };
+    if field()? != b"P6" {
+        return Err("not a binary PPM".into());
+    }
+    let mut number = || -> Result<u32, String> {
+        let text = field()?;
+        std::str::from_utf8(text).ok().and_then(|t| t.parse().ok()).ok_or_else(|| "bad number in PPM header".to_string())
+    };
+    let (width, height, max) = (number()?, number()?, number()?);
+    if !(1..=255).contains(&max) {
+        return Err(format!("PPM maximum value {max} isn't supported"));
+    }
+    let data = bytes.get(pos + 1..).unwrap_or_default();
+    let len = width as usize * height as usize * 3;
+    if data.len() < len {
+        return Err(format!("PPM has {} bytes of pixels, expected {len}", data.len()));
+    }
+    let scale = |v: u8| (v as u32 * 255 / max) as u8;
+    Ok(Image {
+        width,
+        height,
+        pixels: data[..len].chunks_exact(3).flat_map(|p| [scale(p[0]), scale(p[1]), scale(p[2]), 255]).collect(),
+    })
+}
+
+fn rgb_to_rgba(rgb: &[u8]) -> Vec<u8> {
+    rgb.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect()
+}
+
+pub struct Texture {
+    id: GLuint,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Texture {
+    /// Needs a current context.
+    pub fn load(map: &TextureMap) -> Result<Self, String> {
+        let image = decode(&map.path)?;
+        Ok(Self::from_image(&image, map.filter, map.wrap, &map.path.display().to_string()))
+    }
+
+    /// Uploads `image` and builds its mipmaps. Needs a current context.
+    pub fn from_image(image: &Image, filter: Filter, wrap: Wrap, label: &str) -> Self {
+        // GL puts the first row at t = 0, the bottom of the texture.
+        let mut flipped = Image {
+            width: image.width,
+            height: image.height,
+            pixels: image.pixels.clone(),
+        };
+        flipped.flip_vertically();
+
+        let (min_filter, mag_filter) = filter.gl();
+        unsafe {
+            let mut id = 0;
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_2D, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            gl::TexImage2D(
+                gl::TEXTURE_2D,
+                0,
+                gl::RGBA8 as i32,
+                image.width as i32,
+                image.height as i32,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                flipped.pixels.as_ptr() as *const _,
+            );
+            gl::GenerateMipmap(gl::TEXTURE_2D);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, min_filter as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, mag_filter as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, wrap.gl() as i32);
+            gl::TexParameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, wrap.gl() as i32);
+            gl::BindTexture(gl::TEXTURE_2D, 0);
+            gldebug::label(gl::TEXTURE, id, label);
+            Self {
+                id,
+                width: image.width,
+                height: image.height,
+            }
+        }
+    }
+
+    /// Binds to texture unit `unit`, for a sampler uniform set to `unit`.
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_2D, self.id);
+        }
+    }
+}
+
+impl Drop for Texture {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+/// Six square faces sampled by direction, for skyboxes and environment
+/// mapping.
+pub struct CubeMap {
+    id: GLuint,
+    pub size: u32,
+}
+
+impl CubeMap {
+    /// Uploads faces in GL's order, +X, -X, +Y, -Y, +Z, -Z, each with its
+    /// first row at the top as seen from inside the cube, which is how
+    /// cube maps are sampled. Needs a current context.
+    pub fn from_faces(faces: &[Image; 6], label: &str) -> Result<Self, String> {
+        let size = faces[0].width;
+        if let Some(face) = faces.iter().find(|face| (face.width, face.height) != (size, size)) {
+            return Err(format!("cube faces must all be {size}x{size}, not {}x{}", face.width, face.height));
+        }
+        unsafe {
+            let mut id = 0;
+            gl::GenTextures(1, &mut id);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, id);
+            gl::PixelStorei(gl::UNPACK_ALIGNMENT, 1);
+            for (i, face) in faces.iter().enumerate() {
+                gl::TexImage2D(
+                    gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as GLenum,
+                    0,
+                    gl::RGBA8 as i32,
+                    size as i32,
+                    size as i32,
+                    0,
+                    gl::RGBA,
+                    gl::UNSIGNED_BYTE,
+                    face.pixels.as_ptr() as *const _,
+                );
+            }
+            gl::GenerateMipmap(gl::TEXTURE_CUBE_MAP);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MIN_FILTER, gl::LINEAR_MIPMAP_LINEAR as i32);
+            gl::TexParameteri(gl::TEXTURE_CUBE_MAP, gl::TEXTURE_MAG_FILTER, gl::LINEAR as i32);
+            for wrap in [gl::TEXTURE_WRAP_S, gl::TEXTURE_WRAP_T, gl::TEXTURE_WRAP_R] {
+                gl::TexParameteri(gl::TEXTURE_CUBE_MAP, wrap, gl::CLAMP_TO_EDGE as i32);
+            }
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, 0);
+            // Filter across the edges between faces rather than up to them.
+            gl::Enable(gl::TEXTURE_CUBE_MAP_SEAMLESS);
+            gldebug::label(gl::TEXTURE, id, label);
+            Ok(Self { id, size })
+        }
+    }
+
+    /// Binds to texture unit `unit`, for a `samplerCube` set to `unit`.
+    pub fn bind(&self, unit: u32) {
+        unsafe {
+            gl::ActiveTexture(gl::TEXTURE0 + unit);
+            gl::BindTexture(gl::TEXTURE_CUBE_MAP, self.id);
+        }
+    }
+}
+
+impl Drop for CubeMap {
+    fn drop(&mut self) {
+        unsafe {
+            gl::DeleteTextures(1, &self.id);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 2x2: red, green / blue, white at half alpha.
+    fn sample() -> Image {
+        Image {
+            width: 2,
+            height: 2,
+            pixels: vec![255, 0, 0, 255, 0, 255, 0, 255, 0, 0, 255, 255, 255, 255, 255, 128],
+        }
+    }
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rust-test-{}-{name}", std::process::id()))
+    }
+
+    #[test]
+    fn png_round_trips() {
+        let path = temp_path("texture.png");
+        sample().write_png(&path, true).unwrap();
+        let image = decode(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!((image.width, image.height, image.pixels), (2, 2, sample().pixels));
+    }
+
+    #[test]
+    fn ppm_round_trips_opaque() {
+        let path = temp_path("texture.ppm");
+        sample().write_ppm(&path).unwrap();
+        let image = decode(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let mut expected = sample();
+        expected.make_opaque();
+        assert_eq!(image.pixels, expected.pixels);
+    }
+
+    #[test]
+    fn ppm_headers_can_have_comments_and_small_maxima() {
+        let image = decode_ppm(b"P6\n# made by hand\n1 1\n# really\n15\n\x0f\x00\x05").unwrap();
+        assert_eq!(image.pixels, [255, 0, 85, 255]);
+        assert!(decode_ppm(b"P6 2 2 255\n\0\0\0").is_err(), "short pixel data");
+    }
+
+    #[test]
+    fn jpeg_decodes_to_rgba() {
+        // Flat colour survives lossy compression nearly intact.
+        let rgb = [200u8, 100, 50].repeat(16 * 16);
+        let mut jpeg = vec![];
+        jpeg_encoder::Encoder::new(&mut jpeg, 95).encode(&rgb, 16, 16, jpeg_encoder::ColorType::Rgb).unwrap();
+        let image = decode_jpeg(&jpeg).unwrap();
+        assert_eq!((image.width, image.height), (16, 16));
+        for pixel in image.pixels.chunks_exact(4) {
+            assert!(pixel[..3].iter().zip([200u8, 100, 50]).all(|(&a, b)| a.abs_diff(b) <= 3), "{pixel:?}");
+            assert_eq!(pixel[3], 255);
+        }
+    }
+}
@@ -1,32 +1,70 @@
 use std::ffi::CString;
-use std::os::raw::{c_int, c_void};
+use std::os::raw::{c_int, c_long, c_void};
 
 use crate::{x11, glx};
 
-pub fn create(dpy: *mut c_void, width: u16, height: u16) -> Result<x11::Window, Box<dyn std::error::Error>> {
+/// `_NET_WM_STATE` client message actions, see the EWMH spec.
+const NET_WM_STATE_REMOVE: c_long = 0;
+const NET_WM_STATE_ADD: c_long = 1;
+
+/// `_MOTIF_WM_HINTS` flag saying the decorations field is valid.
+const MWM_HINTS_DECORATIONS: c_long = 1 << 1;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WindowMode {
+    pub fullscreen: bool,
+    pub borderless: bool,
+}
+
+/// Size of the default screen in pixels.
+///
+/// # Safety
+///
+/// `dpy` must be an open display.
+pub unsafe fn screen_size(dpy: *mut c_void) -> (u16, u16) {
+    unsafe {
+        let screen = x11::XDefaultScreen(dpy);
+        (
+            x11::XDisplayWidth(dpy, screen) as u16,
+            x11::XDisplayHeight(dpy, screen) as u16,
+        )
+    }
+}
+
+/// A window size that fits comfortably on the screen: three quarters of it,
+/// keeping whatever aspect the monitor has.
+///
+/// # Safety
+///
+/// `dpy` must be an open display.
+pub unsafe fn default_size(dpy: *mut c_void) -> (u16, u16) {
+    let (w, h) = unsafe { screen_size(dpy) };
+    ((w / 4 * 3).max(1), (h / 4 * 3).max(1))
+}
+
+/// Creates and maps the window with the visual of the FBConfig that best
+/// matches `fb_request`. The config is returned so the GL context can be
+/// created against exactly the same one.
+///
+/// # Safety
+///
+/// `dpy` must be an open display.
+pub unsafe fn create(
+    dpy: *mut c_void,
+    width: u16,
+    height: u16,
+    mode: WindowMode,
+    fb_request: &glx::FbConfigRequest,
+) -> Result<(x11::Window, glx::FbConfig), Box<dyn std::error::Error>> {
     unsafe {
         let screen = x11::XDefaultScreen(dpy);
         let root = x11::XDefaultRootWindow(dpy);
+        let (screen_w, screen_h) = screen_size(dpy);
 
-        let fb_attrs = [
-            glx::X_RENDERABLE, 1,
-            glx::DRAWABLE_TYPE, glx::WINDOW_BIT,
-            glx::RENDER_TYPE, glx::RGBA_BIT,
-            glx::RED_SIZE, 8,
-            glx::GREEN_SIZE, 8,
-            glx::BLUE_SIZE, 8,
-            glx::ALPHA_SIZE, 8,
-            glx::DEPTH_SIZE, 24,
-            glx::DOUBLEBUFFER, 1,
-            0,
-        ];
+        let fbconfig = glx::choose_fb_config(dpy, screen, fb_request).ok_or("No FBConfig found")?;
+        println!("Chose FBConfig: {fbconfig}");
 
-        let mut nelements: c_int = 0;
-        let fbconfigs = glx::glXChooseFBConfig(dpy, screen, fb_attrs.as_ptr(), &mut nelements);
-        assert!(!fbconfigs.is_null() && nelements > 0, "No FBConfig found");
-        let fbconfig = *fbconfigs.add(0);
-
-        let visual_info_ptr = glx::glXGetVisualFromFBConfig(dpy, fbconfig);
+        let visual_info_ptr = glx::glXGetVisualFromFBConfig(dpy, fbconfig.raw);
         assert!(!visual_info_ptr.is_null(), "No visual from FBConfig");
         let visual_info: x11::XVisualInfo = *visual_info_ptr;
 
@@ -50,8 +88,8 @@ pub fn create(dpy: *mut c_void, width: u16, height: u16) -> Result<x11::Window,
         let window = x11::XCreateWindow(
             dpy,
             root,
-            100,
-            100,
+            (screen_w.saturating_sub(width) / 2) as i32,
+            (screen_h.saturating_sub(height) / 2) as i32,
             width as u32,
             height as u32,
             0,
@@ -65,8 +103,8 @@ pub fn create(dpy: *mut c_void, width: u16, height: u16) -> Result<x11::Window,
         let title = CString::new("GL Test").unwrap();
         x11::XStoreName(dpy, window, title.as_ptr());
 
-        let net_wm_name = x11::XInternAtom(dpy, b"_NET_WM_NAME\0".as_ptr() as _, 0);
-        let utf8_string = x11::XInternAtom(dpy, b"UTF8_STRING\0".as_ptr() as _, 0);
+        let net_wm_name = x11::XInternAtom(dpy, c"_NET_WM_NAME".as_ptr() as _, 0);
+        let utf8_string = x11::XInternAtom(dpy, c"UTF8_STRING".as_ptr() as _, 0);
         x11::XChangeProperty(
             dpy,
             window,
@@ -78,20 +116,41 @@ pub fn create(dpy: *mut c_void, width: u16, height: u16) -> Result<x11::Window,
             7,
         );
 
-        let net_wm_window_type = x11::XInternAtom(dpy, b"_NET_WM_WINDOW_TYPE\0".as_ptr() as _, 0);
-        let net_wm_window_type_dialog =
-            x11::XInternAtom(dpy, b"_NET_WM_WINDOW_TYPE_DIALOG\0".as_ptr() as _, 0);
+        let net_wm_window_type = x11::XInternAtom(dpy, c"_NET_WM_WINDOW_TYPE".as_ptr() as _, 0);
+        let net_wm_window_type_normal =
+            x11::XInternAtom(dpy, c"_NET_WM_WINDOW_TYPE_NORMAL".as_ptr() as _, 0);
         x11::XChangeProperty(
             dpy,
             window,
             net_wm_window_type,
-            4,
+            x11::XA_ATOM,
             32,
-            0,
-            &net_wm_window_type_dialog as *const _ as *const u8,
+            x11::PROP_MODE_REPLACE,
+            &net_wm_window_type_normal as *const _ as *const u8,
             1,
         );
 
+        if mode.borderless {
+            set_borderless(dpy, window, true);
+        }
+
+        // Before the window is mapped the window manager reads _NET_WM_STATE
+        // straight off the property; afterwards it has to be asked by message.
+        if mode.fullscreen {
+            let net_wm_state = x11::XInternAtom(dpy, c"_NET_WM_STATE".as_ptr() as _, 0);
+            let fullscreen = x11::XInternAtom(dpy, c"_NET_WM_STATE_FULLSCREEN".as_ptr() as _, 0);
+            x11::XChangeProperty(
+                dpy,
+                window,
+                net_wm_state,
+                x11::XA_ATOM,
+                32,
+                x11::PROP_MODE_REPLACE,
+                &fullscreen as *const _ as *const u8,
+                1,
+            );
+        }
+
         x11::XMapWindow(dpy, window);
 
         loop {
@@ -106,9 +165,67 @@ pub fn create(dpy: *mut c_void, width: u16, height: u16) -> Result<x11::Window,
         }
 
         x11::XFree(visual_info_ptr as *mut c_void);
-        x11::XFree(fbconfigs as *mut c_void);
 
         println!("X11 window created via raw Xlib (id: {window})");
-        Ok(window)
+        Ok((window, fbconfig))
+    }
+}
+
+/// Asks the window manager to add or remove `_NET_WM_STATE_FULLSCREEN` on a
+/// mapped window. The resulting resize arrives as a `ConfigureNotify`.
+///
+/// # Safety
+///
+/// `dpy` must be an open display and `window` one of its windows.
+pub unsafe fn set_fullscreen(dpy: *mut c_void, window: x11::Window, enabled: bool) {
+    unsafe {
+        let root = x11::XDefaultRootWindow(dpy);
+        let net_wm_state = x11::XInternAtom(dpy, c"_NET_WM_STATE".as_ptr() as _, 0);
+        let fullscreen = x11::XInternAtom(dpy, c"_NET_WM_STATE_FULLSCREEN".as_ptr() as _, 0);
+
+        let mut ev: x11::XEvent = std::mem::zeroed();
+        let msg = &mut *(&mut ev as *mut x11::XEvent as *mut x11::XClientMessageEvent);
+        msg.type_ = x11::CLIENT_MESSAGE;
+        msg.window = window;
+        msg.message_type = net_wm_state;
+        msg.format = 32;
+        msg.data[0] = if enabled { NET_WM_STATE_ADD } else { NET_WM_STATE_REMOVE };
+        msg.data[1] = fullscreen as c_long;
+        msg.data[2] = 0;
+        msg.data[3] = 1; // source indication: normal application
+
+        x11::XSendEvent(
+            dpy,
+            root,
+            0,
+            x11::SUBSTRUCTURE_REDIRECT_MASK | x11::SUBSTRUCTURE_NOTIFY_MASK,
+            &mut ev,
+        );
+        x11::XFlush(dpy);
+    }
+}
+
+/// Turns window manager decorations off or on through `_MOTIF_WM_HINTS`,
+/// which every common window manager still honours.
+///
+/// # Safety
+///
+/// `dpy` must be an open display and `window` one of its windows.
+pub unsafe fn set_borderless(dpy: *mut c_void, window: x11::Window, enabled: bool) {
+    unsafe {
+        let motif_wm_hints = x11::XInternAtom(dpy, c"_MOTIF_WM_HINTS".as_ptr() as _, 0);
+        // flags, functions, decorations, input_mode, status
+        let hints: [c_long; 5] = [MWM_HINTS_DECORATIONS, 0, if enabled { 0 } else { 1 }, 0, 0];
+        x11::XChangeProperty(
+            dpy,
+            window,
+            motif_wm_hints,
+            motif_wm_hints,
+            32,
+            x11::PROP_MODE_REPLACE,
+            hints.as_ptr() as *const u8,
+            hints.len() as c_int,
+        );
+        x11::XFlush(dpy);
     }
-}
\ No newline at end of file
+}
@@ -5,6 +5,9 @@ pub type Colormap = c_ulong;
 pub type Atom = c_ulong;
 pub type Time = c_ulong;
 pub type VisualID = c_ulong;
+pub type Pixmap = c_ulong;
+pub type Cursor = c_ulong;
+pub type XErrorHandler = extern "C" fn(*mut c_void, *mut c_void) -> c_int;
 
 
 #[repr(C)]
@@ -86,10 +89,58 @@ pub struct XMapEvent {
     pub _padding: [c_char; 12],
 }
 
+#[repr(C)]
+#[derive(Debug)]
+pub struct XConfigureEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: c_int,
+    pub display: *mut c_void,
+    pub event: Window,
+    pub window: Window,
+    pub x: c_int,
+    pub y: c_int,
+    pub width: c_int,
+    pub height: c_int,
+    pub border_width: c_int,
+    pub above: Window,
+    pub override_redirect: c_int,
+    // Pad to 96
+    pub _padding: [c_char; 12],
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct XClientMessageEvent {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: c_int,
+    pub display: *mut c_void,
+    pub window: Window,
+    pub message_type: Atom,
+    pub format: c_int,
+    pub data: [c_long; 5],
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct XGenericEventCookie {
+    pub type_: c_int,
+    pub serial: c_ulong,
+    pub send_event: c_int,
+    pub display: *mut c_void,
+    pub extension: c_int,
+    pub evtype: c_int,
+    pub cookie: c_uint,
+    pub data: *mut c_void,
+}
+
 #[repr(C)]
 #[derive(Debug)]
 pub struct XEvent {
-    pub data: [u8; 96], // sizeof(XEvent) = 96
+    // The C union is 24 longs, 192 bytes on LP64, and Xlib reads and
+    // writes all of it.
+    pub data: [c_long; 24],
 }
 
 pub enum Event<'a> {
@@ -99,6 +150,8 @@ pub enum Event<'a> {
     ButtonRelease(&'a XButtonEvent),
     Motion(&'a XMotionEvent),
     Map(&'a XMapEvent),
+    Configure(&'a XConfigureEvent),
+    Generic,
     DestroyNotify,
     Expose,
     Unknown,
@@ -113,6 +166,8 @@ impl XEvent {
             BUTTON_RELEASE => Event::ButtonRelease(unsafe { &*(self as *const XEvent as *const XButtonEvent) }),
             MOTION_NOTIFY => Event::Motion(unsafe { &*(self as *const XEvent as *const XMotionEvent) }),
             MAP_NOTIFY => Event::Map(unsafe { &*(self as *const XEvent as *const XMapEvent) }),
+            CONFIGURE_NOTIFY => Event::Configure(unsafe { &*(self as *const XEvent as *const XConfigureEvent) }),
+            GENERIC_EVENT => Event::Generic,
             DESTROY_NOTIFY => Event::DestroyNotify,
             EXPOSE => Event::Expose,
             _ => Event::Unknown,
@@ -132,6 +187,9 @@ pub const BUTTON_RELEASE: c_int = 5;
 pub const MOTION_NOTIFY: c_int = 6;
 pub const DESTROY_NOTIFY: c_int = 17;
 pub const MAP_NOTIFY: c_int = 19;
+pub const CONFIGURE_NOTIFY: c_int = 22;
+pub const CLIENT_MESSAGE: c_int = 33;
+pub const GENERIC_EVENT: c_int = 35;
 pub const EXPOSE: c_int = 12;
 
 pub const EXPOSURE_MASK: c_long = 1 << 15;
@@ -141,6 +199,8 @@ pub const KEY_RELEASE_MASK: c_long = 1 << 1;
 pub const BUTTON_PRESS_MASK: c_long = 1 << 2;
 pub const BUTTON_RELEASE_MASK: c_long = 1 << 3;
 pub const POINTER_MOTION_MASK: c_long = 1 << 6;
+pub const SUBSTRUCTURE_NOTIFY_MASK: c_long = 1 << 19;
+pub const SUBSTRUCTURE_REDIRECT_MASK: c_long = 1 << 20;
 
 pub const INPUT_OUTPUT: c_uint = 1;
 pub const CWCOLORMAP: c_ulong = 1 << 13;
@@ -148,6 +208,23 @@ pub const CWEVENT_MASK: c_ulong = 1 << 11;
 
 pub const PROP_MODE_REPLACE: i32 = 0;
 
+pub const XA_ATOM: Atom = 4;
+
+pub const CURRENT_TIME: Time = 0;
+pub const GRAB_MODE_ASYNC: c_int = 1;
+pub const GRAB_SUCCESS: c_int = 0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct XColor {
+    pub pixel: c_ulong,
+    pub red: u16,
+    pub green: u16,
+    pub blue: u16,
+    pub flags: c_char,
+    pub pad: c_char,
+}
+
 #[repr(C)]
 #[derive(Debug, Clone, Copy)]
 pub struct XVisualInfo {
@@ -189,6 +266,8 @@ unsafe extern "C" {
     pub unsafe fn XDefaultScreen(display: *mut c_void) -> c_int;
     pub unsafe fn XDefaultRootWindow(display: *mut c_void) -> Window;
     pub unsafe fn XWhitePixel(display: *mut c_void, screen: c_int) -> c_ulong;
+    pub unsafe fn XDisplayWidth(display: *mut c_void, screen: c_int) -> c_int;
+    pub unsafe fn XDisplayHeight(display: *mut c_void, screen: c_int) -> c_int;
 
     pub unsafe fn XCreateWindow(
         display: *mut c_void,
@@ -219,6 +298,15 @@ unsafe extern "C" {
         nelements: c_int,
     ) -> c_int;
 
+    pub unsafe fn XSendEvent(
+        display: *mut c_void,
+        w: Window,
+        propagate: c_int,
+        event_mask: c_long,
+        event_send: *mut XEvent,
+    ) -> c_int;
+    pub unsafe fn XFlush(display: *mut c_void) -> c_int;
+
     pub unsafe fn XPending(display: *mut c_void) -> c_int;
     pub unsafe fn XNextEvent(display: *mut c_void, event_return: *mut XEvent);
 
@@ -236,5 +324,63 @@ unsafe extern "C" {
         nitems_return: *mut c_int,
     ) -> *mut XVisualInfo;
 
+    pub unsafe fn XGrabPointer(
+        display: *mut c_void,
+        grab_window: Window,
+        owner_events: c_int,
+        event_mask: c_uint,
+        pointer_mode: c_int,
+        keyboard_mode: c_int,
+        confine_to: Window,
+        cursor: Cursor,
+        time: Time,
+    ) -> c_int;
+    pub unsafe fn XUngrabPointer(display: *mut c_void, time: Time) -> c_int;
+    pub unsafe fn XWarpPointer(
+        display: *mut c_void,
+        src_w: Window,
+        dest_w: Window,
+        src_x: c_int,
+        src_y: c_int,
+        src_width: c_uint,
+        src_height: c_uint,
+        dest_x: c_int,
+        dest_y: c_int,
+    ) -> c_int;
+
+    pub unsafe fn XCreateBitmapFromData(
+        display: *mut c_void,
+        d: Window,
+        data: *const c_char,
+        width: c_uint,
+        height: c_uint,
+    ) -> Pixmap;
+    pub unsafe fn XCreatePixmapCursor(
+        display: *mut c_void,
+        source: Pixmap,
+        mask: Pixmap,
+        foreground_color: *mut XColor,
+        background_color: *mut XColor,
+        x: c_uint,
+        y: c_uint,
+    ) -> Cursor;
+    pub unsafe fn XDefineCursor(display: *mut c_void, w: Window, cursor: Cursor) -> c_int;
+    pub unsafe fn XUndefineCursor(display: *mut c_void, w: Window) -> c_int;
+    pub unsafe fn XFreeCursor(display: *mut c_void, cursor: Cursor) -> c_int;
+    pub unsafe fn XFreePixmap(display: *mut c_void, pixmap: Pixmap) -> c_int;
+
+    pub unsafe fn XQueryExtension(
+        display: *mut c_void,
+        name: *const c_char,
+        major_opcode_return: *mut c_int,
+        first_event_return: *mut c_int,
+        first_error_return: *mut c_int,
+    ) -> c_int;
+    pub unsafe fn XGetEventData(display: *mut c_void, cookie: *mut XGenericEventCookie) -> c_int;
+    pub unsafe fn XFreeEventData(display: *mut c_void, cookie: *mut XGenericEventCookie);
+
+    pub unsafe fn XSync(display: *mut c_void, discard: c_int) -> c_int;
+    pub unsafe fn XSetErrorHandler(handler: Option<XErrorHandler>) -> Option<XErrorHandler>;
+
     pub unsafe fn XFree(ptr: *mut c_void);
 }
\ No newline at end of file
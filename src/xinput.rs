@@ -0,0 +1,152 @@
+use std::os::raw::{c_double, c_int, c_uchar, c_void};
+
+use crate::x11;
+
+pub const XI_ALL_MASTER_DEVICES: c_int = 1;
+pub const XI_RAW_MOTION: c_int = 17;
+
+#[repr(C)]
+pub struct XIEventMask {
+    pub deviceid: c_int,
+    pub mask_len: c_int,
+    pub mask: *mut c_uchar,
+}
+
+#[repr(C)]
+pub struct XIValuatorState {
+    pub mask_len: c_int,
+    pub mask: *mut c_uchar,
+    pub values: *mut c_double,
+}
+
+#[repr(C)]
+pub struct XIRawEvent {
+    pub type_: c_int,
+    pub serial: std::os::raw::c_ulong,
+    pub send_event: c_int,
+    pub display: *mut c_void,
+    pub extension: c_int,
+    pub evtype: c_int,
+    pub time: x11::Time,
+    pub deviceid: c_int,
+    pub sourceid: c_int,
+    pub detail: c_int,
+    pub flags: c_int,
+    pub valuators: XIValuatorState,
+    pub raw_values: *mut c_double,
+}
+
+type XIQueryVersionFn = unsafe extern "C" fn(*mut c_void, *mut c_int, *mut c_int) -> c_int;
+type XISelectEventsFn = unsafe extern "C" fn(*mut c_void, x11::Window, *mut XIEventMask, c_int) -> c_int;
+
+/// XInput2, loaded from `libXi.so.6` at runtime so the binary still starts on
+/// machines without it (no dev symlink is needed at build time either).
+pub struct XInput2 {
+    lib: *mut c_void,
+    opcode: c_int,
+    select_events: XISelectEventsFn,
+}
+
+impl XInput2 {
+    /// Returns `None` if the server lacks XInput 2.1 or libXi can't be loaded.
+    /// 2.1 is the first version that delivers raw events during a grab.
+    ///
+    /// # Safety
+    ///
+    /// `dpy` must be an open display.
+    pub unsafe fn load(dpy: *mut c_void) -> Option<Self> {
+        unsafe {
+            let (mut opcode, mut event, mut error) = (0, 0, 0);
+            if x11::XQueryExtension(dpy, c"XInputExtension".as_ptr() as _, &mut opcode, &mut event, &mut error) == 0 {
+                return None;
+            }
+
+            let lib = libc::dlopen(c"libXi.so.6".as_ptr() as _, libc::RTLD_NOW | libc::RTLD_LOCAL);
+            if lib.is_null() {
+                return None;
+            }
+
+            let query_version = libc::dlsym(lib, c"XIQueryVersion".as_ptr() as _);
+            let select_events = libc::dlsym(lib, c"XISelectEvents".as_ptr() as _);
+            if query_version.is_null() || select_events.is_null() {
+                libc::dlclose(lib);
+                return None;
+            }
+            let query_version: XIQueryVersionFn = std::mem::transmute(query_version);
+            let select_events: XISelectEventsFn = std::mem::transmute(select_events);
+
+            let (mut major, mut minor) = (2, 1);
+            if query_version(dpy, &mut major, &mut minor) != 0 || (major, minor) < (2, 1) {
+                libc::dlclose(lib);
+                return None;
+            }
+
+            println!("XInput {major}.{minor} available");
+            Some(Self { lib, opcode, select_events })
+        }
+    }
+
+    /// Raw events are only ever delivered to the root window.
+    ///
+    /// # Safety
+    ///
+    /// `dpy` must be an open display, the one this was loaded on.
+    pub unsafe fn select_raw_motion(&self, dpy: *mut c_void) {
+        unsafe {
+            let mut bits = [0u8; (XI_RAW_MOTION as usize >> 3) + 1];
+            bits[XI_RAW_MOTION as usize >> 3] |= 1 << (XI_RAW_MOTION & 7);
+            let mut mask = XIEventMask {
+                deviceid: XI_ALL_MASTER_DEVICES,
+                mask_len: bits.len() as c_int,
+                mask: bits.as_mut_ptr(),
+            };
+            (self.select_events)(dpy, x11::XDefaultRootWindow(dpy), &mut mask, 1);
+            x11::XFlush(dpy);
+        }
+    }
+
+    /// Unaccelerated pointer delta from an `XI_RawMotion` generic event, or
+    /// `None` if the event is something else.
+    ///
+    /// # Safety
+    ///
+    /// `dpy` must be an open display, the one `ev` came from.
+    pub unsafe fn raw_motion_delta(&self, dpy: *mut c_void, ev: &mut x11::XEvent) -> Option<(f64, f64)> {
+        unsafe {
+            let cookie = &mut *(ev as *mut x11::XEvent as *mut x11::XGenericEventCookie);
+            if cookie.extension != self.opcode || x11::XGetEventData(dpy, cookie) == 0 {
+                return None;
+            }
+
+            let mut delta = None;
+            if cookie.evtype == XI_RAW_MOTION {
+                let raw = &*(cookie.data as *const XIRawEvent);
+                let mut values = raw.raw_values;
+                let (mut dx, mut dy) = (0.0, 0.0);
+                // Only axes whose bit is set have a value, packed in order.
+                for axis in 0..(raw.valuators.mask_len * 8).min(2) {
+                    if *raw.valuators.mask.add(axis as usize >> 3) & (1 << (axis & 7)) != 0 {
+                        if axis == 0 {
+                            dx = *values;
+                        } else {
+                            dy = *values;
+                        }
+                        values = values.add(1);
+                    }
+                }
+                delta = Some((dx, dy));
+            }
+
+            x11::XFreeEventData(dpy, cookie);
+            delta
+        }
+    }
+}
+
+impl Drop for XInput2 {
+    fn drop(&mut self) {
+        unsafe {
+            libc::dlclose(self.lib);
+        }
+    }
+}
@@ -0,0 +1,251 @@
+//! Golden-image tests. Each one renders a known scene off-screen with
+//! Mesa's llvmpipe software rasterizer and compares it against a reference
+//! PNG in `tests/golden/`.
+//!
+//! The default backend is EGL surfaceless, which needs no X server at all.
+//! `GOLDEN_BACKEND=pbuffer` goes through GLX instead, e.g. under
+//! `xvfb-run cargo test`. `UPDATE_GOLDEN=1` rewrites the references from
+//! the current output. On a mismatch the actual image and a diff image are
+//! written next to each other under the target directory.
+
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+const SIZE: (u32, u32) = (160, 120);
+
+/// Frames to render before the capture. Time is simulated in headless
+/// runs, so this pins where the animations are.
+const FRAMES: &str = "30";
+
+/// Largest difference in any channel for two pixels to count as equal.
+const CHANNEL_TOLERANCE: u8 = 16;
+
+/// Share of pixels allowed to differ by more than that, which absorbs
+/// edge rasterization changes between Mesa versions.
+const MAX_BAD_PIXELS: f64 = 0.005;
+
+struct Rgb {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+fn read_png(path: &Path) -> Rgb {
+    let file = File::open(path).unwrap_or_else(|e| panic!("can't open {}: {e}", path.display()));
+    let mut reader = png::Decoder::new(file).read_info().unwrap();
+    let mut buf = vec![0; reader.output_buffer_size()];
+    let info = reader.next_frame(&mut buf).unwrap();
+    assert_eq!(info.color_type, png::ColorType::Rgb, "{} isn't an RGB PNG", path.display());
+    buf.truncate(info.buffer_size());
+    Rgb {
+        width: info.width,
+        height: info.height,
+        pixels: buf,
+    }
+}
+
+fn write_png(path: &Path, image: &Rgb) {
+    let out = BufWriter::new(File::create(path).unwrap());
+    let mut encoder = png::Encoder::new(out, image.width, image.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header().unwrap();
+    writer.write_image_data(&image.pixels).unwrap();
+}
+
+fn diff_dir() -> PathBuf {
+    Path::new(env!("CARGO_TARGET_TMPDIR")).join("golden-diff")
+}
+
+/// Runs the binary headless with `args` and returns the frame it wrote.
+fn render(name: &str, args: &[&str]) -> PathBuf {
+    let dir = diff_dir();
+    std::fs::create_dir_all(&dir).unwrap();
+    let output = dir.join(format!("{name}-actual.png"));
+    let size = format!("{}x{}", SIZE.0, SIZE.1);
+    let backend = std::env::var("GOLDEN_BACKEND").unwrap_or_else(|_| "egl".into());
+
+    let result = Command::new(env!("CARGO_BIN_EXE_rust-test"))
+        .args(["--headless", "--headless-backend", &backend, "--frames", FRAMES, "--size", &size])
+        .arg("--output")
+        .arg(&output)
+        .args(args)
+        .env("LIBGL_ALWAYS_SOFTWARE", "1")
+        .env("GALLIUM_DRIVER", "llvmpipe")
+        .current_dir(env!("CARGO_MANIFEST_DIR"))
+        .output()
+        .expect("failed to run rust-test");
+    assert!(
+        result.status.success(),
+        "rendering {name} with the {backend} backend failed:\n{}{}",
+        String::from_utf8_lossy(&result.stdout),
+        String::from_utf8_lossy(&result.stderr),
+    );
+    output
+}
+
+/// Renders `name` and compares it against `tests/golden/<name>.png`.
+fn check(name: &str, args: &[&str]) {
+    let actual_path = render(name, args);
+    let reference_path = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden").join(format!("{name}.png"));
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        std::fs::copy(&actual_path, &reference_path).unwrap();
+        return;
+    }
+
+    let actual = read_png(&actual_path);
+    let expected = read_png(&reference_path);
+    assert_eq!((actual.width, actual.height), (expected.width, expected.height), "{name}: size changed");
+
+    // Bad pixels in red, everything else as a faded copy of the reference.
+    let mut diff = Rgb {
+        width: actual.width,
+        height: actual.height,
+        pixels: Vec::with_capacity(actual.pixels.len()),
+    };
+    let mut bad = 0;
+    for (a, e) in actual.pixels.chunks_exact(3).zip(expected.pixels.chunks_exact(3)) {
+        let worst = a.iter().zip(e).map(|(&a, &e)| a.abs_diff(e)).max().unwrap();
+        if worst > CHANNEL_TOLERANCE {
+            bad += 1;
+            diff.pixels.extend([255, 0, 0]);
+        } else {
+            let grey = ((e[0] as u32 + e[1] as u32 + e[2] as u32) / 12) as u8;
+            diff.pixels.extend([grey; 3]);
+        }
+    }
+
+    let total = (actual.width * actual.height) as f64;
+    if bad as f64 / total > MAX_BAD_PIXELS {
+        let diff_path = diff_dir().join(format!("{name}-diff.png"));
+        write_png(&diff_path, &diff);
+        panic!(
+            "{name}: {bad} of {total} pixels differ from {}\n  actual: {}\n  diff:   {}",
+            reference_path.display(),
+            actual_path.display(),
+            diff_path.display(),
+        );
+    }
+}
+
+#[test]
+fn default_scene() {
+    check("default", &[]);
+}
+
+#[test]
+fn cube_vertex_colours() {
+    check("cube-0", &["--shape", "cube", "--colour-mode", "0"]);
+}
+
+#[test]
+fn cube_position_colours() {
+    check("cube-1", &["--shape", "cube", "--colour-mode", "1"]);
+}
+
+#[test]
+fn pyramid_vertex_colours() {
+    check("pyramid-0", &["--shape", "pyramid", "--colour-mode", "0"]);
+}
+
+#[test]
+fn pyramid_position_colours() {
+    check("pyramid-1", &["--shape", "pyramid", "--colour-mode", "1"]);
+}
+
+#[test]
+fn sphere_vertex_colours() {
+    check("sphere-0", &["--shape", "sphere", "--colour-mode", "0"]);
+}
+
+#[test]
+fn sphere_position_colours() {
+    check("sphere-1", &["--shape", "sphere", "--colour-mode", "1"]);
+}
+
+#[test]
+fn cube_with_front_faces_culled() {
+    check("cube-cull-front", &["--shape", "cube", "--cull", "front"]);
+}
+
+#[test]
+fn back_face_view_with_clockwise_fronts() {
+    check("pyramid-back-faces", &["--shape", "pyramid", "--front-face", "cw", "--show-back-faces"]);
+}
+
+#[test]
+fn robot_arm_scene_file() {
+    check("robot-arm", &["--scene", "scenes/robot-arm.ron"]);
+}
+
+#[test]
+fn directional_point_and_spot_lights() {
+    check("lights", &["--scene", "scenes/lights.ron"]);
+}
+
+#[test]
+fn materials_from_an_mtl_library() {
+    check("showroom", &["--scene", "scenes/showroom.ron"]);
+}
+
+#[test]
+fn flat_shading() {
+    check("lights-flat", &["--scene", "scenes/lights.ron", "--shading", "flat"]);
+}
+
+#[test]
+fn gouraud_shading() {
+    check("lights-gouraud", &["--scene", "scenes/lights.ron", "--shading", "gouraud"]);
+}
+
+#[test]
+fn blinn_phong_shading_with_attenuation() {
+    check("lights-blinn-phong", &["--scene", "scenes/lights.ron", "--shading", "blinn-phong", "--attenuation"]);
+}
+
+#[test]
+fn textured_shapes() {
+    check("textured", &["--scene", "scenes/textured.ron"]);
+}
+
+#[test]
+fn normal_mapped_planet() {
+    check("planet", &["--scene", "scenes/planet.ron"]);
+}
+
+#[test]
+fn directional_and_spot_shadows() {
+    check("shadows", &["--scene", "scenes/shadows.ron"]);
+}
+
+#[test]
+fn shadow_map_debug_view() {
+    check("shadow-map-view", &["--scene", "scenes/shadows.ron", "--show-shadow-map", "--shadow-pcf", "0"]);
+}
+
+#[test]
+fn point_light_shadows() {
+    check("point-shadows", &["--scene", "scenes/point-shadows.ron", "--attenuation"]);
+}
+
+#[test]
+fn point_light_shadows_a_face_at_a_time() {
+    check("point-shadows-six-pass", &["--scene", "scenes/point-shadows.ron", "--attenuation", "--point-shadow-passes", "6"]);
+}
+
+#[test]
+fn equirectangular_skybox_with_reflection_and_refraction() {
+    check("environment", &["--scene", "scenes/environment.ron"]);
+}
+
+#[test]
+fn six_face_skybox() {
+    check("cubemap-room", &["--scene", "scenes/cubemap-room.ron"]);
+}
+
+#[test]
+fn screens_show_rendered_views() {
+    check("screens", &["--scene", "scenes/screens.ron"]);
+}